@@ -15,7 +15,7 @@ use cryptoxide::sha2::Sha512;
 
 use std::{
     fmt,
-    io::{BufRead, Write},
+    io::{self, BufRead, Read, Write},
     ops::Deref,
 };
 
@@ -34,6 +34,8 @@ const TAG_LEN: usize = 16;
 
 #[derive(Debug)]
 pub enum Error {
+    /// the bytes given to build an `HDKey` are not `HDKEY_SIZE` long,
+    /// holds the number of bytes received
     InvalidHDKeySize(usize),
     CannotDecrypt,
     NotEnoughEncryptedData,
@@ -41,24 +43,41 @@ pub enum Error {
     /// can have an infinite length (as long as it fits in the max block size
     /// and max transaction size).
     PayloadIsTooLarge(usize),
+    /// the index given to build a derivation path already has the
+    /// hardened bit set
+    InvalidDerivationIndex(u32),
     CborError(cbor_event::Error),
+    IoError(io::Error),
 }
 impl From<cbor_event::Error> for Error {
     fn from(e: cbor_event::Error) -> Self {
         Error::CborError(e)
     }
 }
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Error::InvalidHDKeySize(sz) => {
-                write!(f, "Invalid size for an HDKey, expecting {} bytes", sz)
-            }
+            Error::InvalidHDKeySize(sz) => write!(
+                f,
+                "Invalid size for an HDKey, expecting {} bytes but received {}",
+                HDKEY_SIZE, sz
+            ),
             Error::CannotDecrypt => write!(f, "Cannot decrypt HDPayload with given HDKey"),
             Error::NotEnoughEncryptedData => {
                 write!(f, "Invalid HDPayload, expecting at least {} bytes", TAG_LEN)
             }
+            Error::InvalidDerivationIndex(index) => write!(
+                f,
+                "Invalid derivation index {}, expecting an index below {}",
+                index, HARDENED_INDEX
+            ),
             Error::CborError(_) => write!(f, "HDPayload decrypted but invalid value"),
+            Error::IoError(_) => write!(f, "I/O error while reading the encrypted data"),
             Error::PayloadIsTooLarge(len) => write!(
                 f,
                 "HDPayload is too large to be valid. Its size {} is beyond the max size ({} bytes)",
@@ -71,11 +90,62 @@ impl ::std::error::Error for Error {
     fn cause(&self) -> Option<&::std::error::Error> {
         match self {
             Error::CborError(ref err) => Some(err),
+            Error::IoError(ref err) => Some(err),
             _ => None,
         }
     }
 }
 
+/// Error returned by `HDAddressPayload::try_from_bytes` when the bytes
+/// cannot be an encrypted derivation path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayloadError {
+    /// the payload is not longer than the MAC tag, so there is nothing
+    /// to decrypt
+    TooShort(usize),
+    /// the payload is too large for `HDKey::decrypt` to accept it
+    TooLong(usize),
+}
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadError::TooShort(len) => write!(
+                f,
+                "HDPayload of {} bytes is too short, expecting more than {} bytes",
+                len, TAG_LEN
+            ),
+            PayloadError::TooLong(len) => write!(
+                f,
+                "HDPayload of {} bytes is too long, expecting less than {} bytes",
+                len,
+                MAX_PAYLOAD_SIZE + TAG_LEN
+            ),
+        }
+    }
+}
+impl ::std::error::Error for PayloadError {}
+
+/// Error returned by `Path::from_bip32_string`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathParseError {
+    /// the path does not start with the `m` root
+    MissingRoot,
+    /// the segment is not an index below `HARDENED_INDEX`, optionally
+    /// followed by `'`
+    InvalidSegment(String),
+}
+impl fmt::Display for PathParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathParseError::MissingRoot => write!(f, "derivation path must start with 'm'"),
+            PathParseError::InvalidSegment(segment) => {
+                write!(f, "invalid derivation path segment '{}'", segment)
+            }
+        }
+    }
+}
+impl ::std::error::Error for PathParseError {}
+
 /// This is the max size we accept to try to decrypt a HDPayload.
 /// This is due to avoid trying to decrypt content that are way beyond
 /// reasonable size.
@@ -83,6 +153,9 @@ pub const MAX_PAYLOAD_SIZE: usize = 48;
 
 pub type Result<T> = ::std::result::Result<T, Error>;
 
+/// the bit set on the derivation indices of hardened derivations
+pub const HARDENED_INDEX: u32 = 0x8000_0000;
+
 /// A derivation path of HD wallet derivation indices which uses a CBOR encoding
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 #[cfg_attr(feature = "generic-serialization", derive(Serialize, Deserialize))]
@@ -102,6 +175,72 @@ impl Path {
     pub fn new(v: Vec<u32>) -> Self {
         Path(v)
     }
+    /// append a hardened derivation with the given index, which must be
+    /// below `HARDENED_INDEX`
+    pub fn push_hardened(&mut self, index: u32) -> Result<()> {
+        if index >= HARDENED_INDEX {
+            return Err(Error::InvalidDerivationIndex(index));
+        }
+        self.0.push(index | HARDENED_INDEX);
+        Ok(())
+    }
+    /// append a soft derivation with the given index, which must be
+    /// below `HARDENED_INDEX`
+    pub fn push_soft(&mut self, index: u32) -> Result<()> {
+        if index >= HARDENED_INDEX {
+            return Err(Error::InvalidDerivationIndex(index));
+        }
+        self.0.push(index);
+        Ok(())
+    }
+    /// tell whether the derivation at the given position in the path is
+    /// hardened, `None` if the path is shorter
+    pub fn is_hardened(&self, index_position: usize) -> Option<bool> {
+        self.0
+            .get(index_position)
+            .map(|index| index & HARDENED_INDEX != 0)
+    }
+    /// format the path as a BIP32 derivation path string, for example
+    /// `m/44'/1815'/0'/0/0`, hardened indices being marked with `'`
+    pub fn to_bip32_string(&self) -> String {
+        let mut s = String::from("m");
+        for index in self.0.iter() {
+            if index & HARDENED_INDEX != 0 {
+                s.push_str(&format!("/{}'", index & !HARDENED_INDEX));
+            } else {
+                s.push_str(&format!("/{}", index));
+            }
+        }
+        s
+    }
+    /// parse a BIP32 derivation path string, as formatted by
+    /// `to_bip32_string`. Every index must be below `HARDENED_INDEX`,
+    /// the hardened bit being set by the `'` suffix.
+    pub fn from_bip32_string(s: &str) -> ::std::result::Result<Self, PathParseError> {
+        let mut segments = s.split('/');
+        if segments.next() != Some("m") {
+            return Err(PathParseError::MissingRoot);
+        }
+        let mut path = Path::new(Vec::new());
+        for segment in segments {
+            let invalid = || PathParseError::InvalidSegment(segment.to_string());
+            let (digits, hardened) = match segment.strip_suffix('\'') {
+                Some(digits) => (digits, true),
+                None => (segment, false),
+            };
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            let index = digits.parse::<u32>().map_err(|_| invalid())?;
+            let pushed = if hardened {
+                path.push_hardened(index)
+            } else {
+                path.push_soft(index)
+            };
+            pushed.map_err(|_| invalid())?;
+        }
+        Ok(path)
+    }
     fn from_cbor(bytes: &[u8]) -> Result<Self> {
         let cursor = std::io::Cursor::new(bytes);
         let mut raw = Deserializer::from(cursor);
@@ -150,7 +289,8 @@ impl HDKey {
     pub fn from_bytes(bytes: [u8; HDKEY_SIZE]) -> Self {
         HDKey(bytes)
     }
-    /// create a `HDKey` from the given slice
+    /// create a `HDKey` from the given slice, failing with
+    /// `Error::InvalidHDKeySize` if it is not `HDKEY_SIZE` bytes long
     pub fn from_slice(bytes: &[u8]) -> Result<Self> {
         if bytes.len() == HDKEY_SIZE {
             let mut v = [0u8; HDKEY_SIZE];
@@ -162,7 +302,15 @@ impl HDKey {
     }
 
     pub fn encrypt(&self, input: &[u8]) -> Vec<u8> {
-        let mut ctx = ChaCha20Poly1305::new(self.as_ref(), &NONCE[..], &[]);
+        self.encrypt_with_aad(input, &[])
+    }
+
+    /// encrypt the input, authenticating the associated data `aad`
+    /// along with it. The associated data is not part of the output: the
+    /// same `aad` has to be given to `decrypt_with_aad`, which binds the
+    /// payload to its context (e.g. the address it belongs to).
+    pub fn encrypt_with_aad(&self, input: &[u8], aad: &[u8]) -> Vec<u8> {
+        let mut ctx = ChaCha20Poly1305::new(self.as_ref(), &NONCE[..], aad);
 
         let len = input.len();
 
@@ -174,7 +322,22 @@ impl HDKey {
         out
     }
 
+    /// decrypt and authenticate the ciphertext followed by its tag.
+    ///
+    /// Besides the checks on the length of the input, cryptoxide computes
+    /// the tag of the ciphertext and compares it in constant time with the
+    /// given one, and only decrypts the ciphertext if they match. A payload
+    /// with a bad tag is therefore rejected faster than a valid one is
+    /// decrypted, but the time taken does not tell how much of the tag
+    /// was right. The plaintext is only returned when the tag matches.
     pub fn decrypt(&self, input: &[u8]) -> Result<Vec<u8>> {
+        self.decrypt_with_aad(input, &[])
+    }
+
+    /// same as `decrypt`, for a payload encrypted with `encrypt_with_aad`.
+    /// Fails with `Error::CannotDecrypt` if `aad` is not the associated
+    /// data given at encryption.
+    pub fn decrypt_with_aad(&self, input: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
         if input.len() <= TAG_LEN {
             return Err(Error::NotEnoughEncryptedData);
         };
@@ -183,7 +346,7 @@ impl HDKey {
             return Err(Error::PayloadIsTooLarge(len));
         }
 
-        let mut ctx = ChaCha20Poly1305::new(self.as_ref(), &NONCE[..], &[]);
+        let mut ctx = ChaCha20Poly1305::new(self.as_ref(), &NONCE[..], aad);
 
         let mut out: Vec<u8> = vec![0; len];
 
@@ -194,6 +357,39 @@ impl HDKey {
         }
     }
 
+    /// encrypt the given input into the writer, writing the ciphertext
+    /// followed by the `TAG_LEN` bytes tag. The output is the same as
+    /// `encrypt`'s.
+    ///
+    /// This is not a streaming encryption: the whole ciphertext is
+    /// computed in memory before being written. As every payload is
+    /// encrypted under the same nonce, this is only meant for small
+    /// payloads like `encrypt`, and an input of `MAX_PAYLOAD_SIZE` bytes
+    /// or more, that `decrypt_from` would reject, is refused.
+    pub fn encrypt_to<W: Write>(&self, input: &[u8], mut out: W) -> io::Result<()> {
+        if input.len() >= MAX_PAYLOAD_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{}", Error::PayloadIsTooLarge(input.len())),
+            ));
+        }
+        out.write_all(&self.encrypt(input))
+    }
+
+    /// decrypt the ciphertext followed by its tag, as written by
+    /// `encrypt_to`, from the given reader.
+    ///
+    /// At most `MAX_PAYLOAD_SIZE + TAG_LEN + 1` bytes are read, enough to
+    /// tell an oversized payload apart, and the result goes through the
+    /// same checks as `decrypt`.
+    pub fn decrypt_from<R: Read>(&self, input: R) -> Result<Vec<u8>> {
+        let mut payload = Vec::new();
+        input
+            .take((MAX_PAYLOAD_SIZE + TAG_LEN + 1) as u64)
+            .read_to_end(&mut payload)?;
+        self.decrypt(&payload)
+    }
+
     pub fn encrypt_path(&self, derivation_path: &Path) -> HDAddressPayload {
         let input = derivation_path.cbor();
         let out = self.encrypt(&input);
@@ -205,6 +401,23 @@ impl HDKey {
         let out = self.decrypt(payload.as_ref())?;
         Path::from_cbor(&out)
     }
+
+    /// recognize an address payload encrypted with this key, returning
+    /// the derivation path it holds. `None` if the payload does not belong
+    /// to the wallet this key was derived from.
+    pub fn recognize(&self, payload: &HDAddressPayload) -> Option<Path> {
+        self.decrypt_path(payload).ok()
+    }
+
+    /// `recognize` each of the given payloads, in order. Payloads whose
+    /// length cannot hold an encrypted path are rejected by the decryption
+    /// before anything is allocated for them.
+    pub fn recognize_many(&self, payloads: &[HDAddressPayload]) -> Vec<Option<Path>> {
+        payloads
+            .iter()
+            .map(|payload| self.recognize(payload))
+            .collect()
+    }
 }
 impl Drop for HDKey {
     fn drop(&mut self) {
@@ -225,12 +438,27 @@ impl AsRef<[u8]> for HDAddressPayload {
     }
 }
 impl HDAddressPayload {
+    /// Build a payload from bytes that are not checked: the payload may
+    /// hold anything, including non encrypted information.
     pub fn from_vec(v: Vec<u8>) -> Self {
         HDAddressPayload(v)
     }
     pub fn from_bytes(bytes: &[u8]) -> Self {
         HDAddressPayload::from_vec(bytes.iter().cloned().collect())
     }
+    /// Build a payload that may hold an encrypted derivation path: it
+    /// must be longer than the `TAG_LEN` bytes MAC tag, and the encrypted
+    /// data must be smaller than `MAX_PAYLOAD_SIZE`, the limits enforced
+    /// by `HDKey::decrypt`.
+    pub fn try_from_bytes(bytes: &[u8]) -> ::std::result::Result<Self, PayloadError> {
+        if bytes.len() <= TAG_LEN {
+            return Err(PayloadError::TooShort(bytes.len()));
+        }
+        if bytes.len() >= MAX_PAYLOAD_SIZE + TAG_LEN {
+            return Err(PayloadError::TooLong(bytes.len()));
+        }
+        Ok(HDAddressPayload::from_bytes(bytes))
+    }
     pub fn len(&self) -> usize {
         self.0.len()
     }
@@ -280,6 +508,110 @@ mod tests {
         assert_eq!(bytes, key.decrypt(&payload).unwrap())
     }
 
+    #[test]
+    fn hdkey_from_slice_wrong_length() {
+        match HDKey::from_slice(&[0; HDKEY_SIZE - 1]) {
+            Err(Error::InvalidHDKeySize(got)) => assert_eq!(got, 31),
+            r => panic!("expecting Error::InvalidHDKeySize but got {:#?}", r),
+        }
+        assert_eq!(
+            Error::InvalidHDKeySize(31).to_string(),
+            "Invalid size for an HDKey, expecting 32 bytes but received 31"
+        );
+        assert!(HDKey::from_slice(&[0; HDKEY_SIZE]).is_ok());
+    }
+
+    #[test]
+    fn encrypt_with_aad() {
+        let seed = hdwallet::Seed::from_bytes([0; hdwallet::SEED_SIZE]);
+        let sk = hdwallet::XPrv::generate_from_seed(&seed);
+        let key = HDKey::new(&sk.public());
+        let bytes = [42u8; 32];
+
+        let payload = key.encrypt_with_aad(&bytes, b"address 1");
+        assert_eq!(
+            &bytes[..],
+            &key.decrypt_with_aad(&payload, b"address 1").unwrap()[..]
+        );
+        match key.decrypt_with_aad(&payload, b"address 2").unwrap_err() {
+            Error::CannotDecrypt => {}
+            err => panic!("expecting Error::CannotDecrypt but got {:#?}", err),
+        }
+        match key.decrypt(&payload).unwrap_err() {
+            Error::CannotDecrypt => {}
+            err => panic!("expecting Error::CannotDecrypt but got {:#?}", err),
+        }
+        assert_eq!(key.encrypt_with_aad(&bytes, &[]), key.encrypt(&bytes));
+    }
+
+    #[test]
+    fn encrypt_to_matches_encrypt() {
+        let seed = hdwallet::Seed::from_bytes([0; hdwallet::SEED_SIZE]);
+        let sk = hdwallet::XPrv::generate_from_seed(&seed);
+        let key = HDKey::new(&sk.public());
+
+        for size in &[1, 42, MAX_PAYLOAD_SIZE - 1] {
+            let bytes: Vec<u8> = (0..*size).map(|i| i as u8).collect();
+            let mut streamed = Vec::new();
+            key.encrypt_to(&bytes, &mut streamed).unwrap();
+            assert_eq!(streamed, key.encrypt(&bytes));
+            assert_eq!(bytes, key.decrypt_from(streamed.as_slice()).unwrap());
+        }
+    }
+
+    #[test]
+    fn decrypt_bad_tag() {
+        let seed = hdwallet::Seed::from_bytes([0; hdwallet::SEED_SIZE]);
+        let sk = hdwallet::XPrv::generate_from_seed(&seed);
+        let key = HDKey::new(&sk.public());
+
+        let mut payload = key.encrypt(&[42u8; 42]);
+        let last = payload.len() - 1;
+        payload[last] ^= 1;
+        match key.decrypt(&payload).unwrap_err() {
+            Error::CannotDecrypt => {}
+            err => panic!("expecting Error::CannotDecrypt but got {:#?}", err),
+        }
+    }
+
+    #[test]
+    fn decrypt_from_invalid() {
+        let seed = hdwallet::Seed::from_bytes([0; hdwallet::SEED_SIZE]);
+        let sk = hdwallet::XPrv::generate_from_seed(&seed);
+        let key = HDKey::new(&sk.public());
+
+        let mut payload = key.encrypt(&[42u8; 42]);
+        payload[10] ^= 1;
+        match key.decrypt_from(payload.as_slice()).unwrap_err() {
+            Error::CannotDecrypt => {}
+            err => panic!("expecting Error::CannotDecrypt but got {:#?}", err),
+        }
+        match key.decrypt_from(&payload[..TAG_LEN]).unwrap_err() {
+            Error::NotEnoughEncryptedData => {}
+            err => panic!("expecting Error::NotEnoughEncryptedData but got {:#?}", err),
+        }
+    }
+
+    #[test]
+    fn decrypt_from_too_large() {
+        let seed = hdwallet::Seed::from_bytes([0; hdwallet::SEED_SIZE]);
+        let sk = hdwallet::XPrv::generate_from_seed(&seed);
+        let key = HDKey::new(&sk.public());
+
+        // a reader that never ends is only read up to the limit
+        match key.decrypt_from(io::repeat(42)).unwrap_err() {
+            Error::PayloadIsTooLarge(len) => assert_eq!(len, MAX_PAYLOAD_SIZE + 1),
+            err => panic!("expecting Error::PayloadIsTooLarge but got {:#?}", err),
+        }
+
+        let mut out = Vec::new();
+        let err = key
+            .encrypt_to(&[42u8; MAX_PAYLOAD_SIZE], &mut out)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(out.is_empty());
+    }
+
     #[test]
     fn decrypt_too_small() {
         const TOO_SMALL_PAYLOAD: usize = TAG_LEN - 1;
@@ -318,6 +650,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn payload_try_from_bytes_boundaries() {
+        const MAX_LEN: usize = MAX_PAYLOAD_SIZE + TAG_LEN - 1;
+        assert_eq!(
+            HDAddressPayload::try_from_bytes(&[]),
+            Err(PayloadError::TooShort(0))
+        );
+        assert_eq!(
+            HDAddressPayload::try_from_bytes(&[0; TAG_LEN]),
+            Err(PayloadError::TooShort(TAG_LEN))
+        );
+        assert_eq!(
+            HDAddressPayload::try_from_bytes(&[0; TAG_LEN + 1]),
+            Ok(HDAddressPayload::from_bytes(&[0; TAG_LEN + 1]))
+        );
+        assert_eq!(
+            HDAddressPayload::try_from_bytes(&[0; MAX_LEN]),
+            Ok(HDAddressPayload::from_bytes(&[0; MAX_LEN]))
+        );
+        assert_eq!(
+            HDAddressPayload::try_from_bytes(&[0; MAX_LEN + 1]),
+            Err(PayloadError::TooLong(MAX_LEN + 1))
+        );
+    }
+
+    #[test]
+    fn payload_try_from_bytes_accepts_encrypted_data() {
+        let seed = hdwallet::Seed::from_bytes([0; hdwallet::SEED_SIZE]);
+        let sk = hdwallet::XPrv::generate_from_seed(&seed);
+        let key = HDKey::new(&sk.public());
+        let input = [1, 2, 3];
+        let encrypted = key.encrypt(&input);
+
+        let payload = HDAddressPayload::try_from_bytes(&encrypted).unwrap();
+        assert_eq!(key.decrypt(payload.as_ref()).unwrap(), input);
+    }
+
+    #[test]
+    fn recognize() {
+        let wallet = hdwallet::XPrv::generate_from_seed(&hdwallet::Seed::from_bytes(
+            [0; hdwallet::SEED_SIZE],
+        ));
+        let other = hdwallet::XPrv::generate_from_seed(&hdwallet::Seed::from_bytes(
+            [1; hdwallet::SEED_SIZE],
+        ));
+        let key = HDKey::new(&wallet.public());
+        let other_key = HDKey::new(&other.public());
+        let path = Path::new(vec![0x80000000, 2]);
+        let payload = key.encrypt_path(&path);
+
+        assert_eq!(key.recognize(&payload), Some(path.clone()));
+        assert_eq!(other_key.recognize(&payload), None);
+
+        let payloads = [
+            other_key.encrypt_path(&path),
+            HDAddressPayload::from_bytes(&[0; TAG_LEN]),
+            payload,
+        ];
+        assert_eq!(key.recognize_many(&payloads), vec![None, None, Some(path)]);
+    }
+
     #[test]
     fn path_cbor_encoding() {
         let path = Path::new(vec![0, 1, 2]);
@@ -325,6 +718,78 @@ mod tests {
         assert_eq!(path, Path::from_cbor(cbor.as_ref()).unwrap());
     }
 
+    #[test]
+    fn path_bip32_string() {
+        let mut path = Path::new(Vec::new());
+        assert_eq!(path.to_bip32_string(), "m");
+        assert_eq!(Path::from_bip32_string("m"), Ok(path.clone()));
+
+        path.push_hardened(44).unwrap();
+        path.push_hardened(1815).unwrap();
+        path.push_hardened(0).unwrap();
+        path.push_soft(0).unwrap();
+        path.push_soft(HARDENED_INDEX - 1).unwrap();
+        let s = path.to_bip32_string();
+        assert_eq!(s, "m/44'/1815'/0'/0/2147483647");
+        assert_eq!(Path::from_bip32_string(&s), Ok(path));
+    }
+
+    #[test]
+    fn path_bip32_string_malformed() {
+        assert_eq!(
+            Path::from_bip32_string(""),
+            Err(PathParseError::MissingRoot)
+        );
+        assert_eq!(
+            Path::from_bip32_string("44'/0"),
+            Err(PathParseError::MissingRoot)
+        );
+        for segment in &["", "'", "a", "-1", "+1", "1''", "2147483648", "4294967296"] {
+            assert_eq!(
+                Path::from_bip32_string(&format!("m/0/{}", segment)),
+                Err(PathParseError::InvalidSegment(segment.to_string())),
+                "segment '{}'",
+                segment
+            );
+        }
+    }
+
+    #[test]
+    fn path_hardened_and_soft() {
+        let mut path = Path::new(Vec::new());
+        path.push_hardened(0).unwrap();
+        path.push_soft(1).unwrap();
+        path.push_hardened(HARDENED_INDEX - 1).unwrap();
+        assert_eq!(path.as_ref(), &[HARDENED_INDEX, 1, u32::MAX]);
+        assert_eq!(path.is_hardened(0), Some(true));
+        assert_eq!(path.is_hardened(1), Some(false));
+        assert_eq!(path.is_hardened(2), Some(true));
+        assert_eq!(path.is_hardened(3), None);
+
+        match path.push_hardened(HARDENED_INDEX).unwrap_err() {
+            Error::InvalidDerivationIndex(index) => assert_eq!(index, HARDENED_INDEX),
+            err => panic!("expecting Error::InvalidDerivationIndex but got {:#?}", err),
+        }
+        match path.push_soft(HARDENED_INDEX | 1).unwrap_err() {
+            Error::InvalidDerivationIndex(index) => assert_eq!(index, HARDENED_INDEX | 1),
+            err => panic!("expecting Error::InvalidDerivationIndex but got {:#?}", err),
+        }
+        assert_eq!(path.len(), 3);
+    }
+
+    #[test]
+    fn path_hardened_and_soft_cbor_encoding() {
+        let mut soft = Path::new(Vec::new());
+        soft.push_soft(0).unwrap();
+        soft.push_soft(42).unwrap();
+        assert_eq!(soft, Path::from_cbor(soft.cbor().as_ref()).unwrap());
+
+        let mut hardened = Path::new(Vec::new());
+        hardened.push_hardened(0).unwrap();
+        hardened.push_hardened(42).unwrap();
+        assert_eq!(hardened, Path::from_cbor(hardened.cbor().as_ref()).unwrap());
+    }
+
     #[test]
     fn hdpayload() {
         let path = Path::new(vec![0, 1, 2]);
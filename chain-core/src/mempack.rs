@@ -41,6 +41,23 @@ pub enum ReadError {
     StructureInvalid(String),
     /// Unknown enumeration tag
     UnknownTag(u32),
+    /// The wrapped error occurred on the read starting at the given byte
+    /// offset of the input
+    AtPosition(usize, Box<ReadError>),
+    /// The wrapped error occurred while reading the labelled field
+    Context(&'static str, Box<ReadError>),
+}
+
+impl ReadError {
+    /// Move the position of the error by `base` bytes, for errors located
+    /// in a buffer that starts `base` bytes into the enclosing input.
+    /// Errors without a position are left as they are.
+    pub fn shift_position(self, base: usize) -> Self {
+        match self {
+            ReadError::AtPosition(position, e) => ReadError::AtPosition(base + position, e),
+            e => e,
+        }
+    }
 }
 
 impl fmt::Display for ReadError {
@@ -59,6 +76,8 @@ impl fmt::Display for ReadError {
             ),
             ReadError::StructureInvalid(s) => write!(f, "Structure invalid: {}", s),
             ReadError::UnknownTag(t) => write!(f, "Unknown tag: {}", t),
+            ReadError::AtPosition(position, e) => write!(f, "at byte {}: {}", position, e),
+            ReadError::Context(label, e) => write!(f, "while reading {}: {}", label, e),
         }
     }
 }
@@ -68,6 +87,8 @@ impl Error for ReadError {}
 /// A local memory slice to read from memory
 pub struct ReadBuf<'a> {
     offset: usize,
+    /// offset at which the latest read started
+    last_read: usize,
     data: &'a [u8],
 }
 
@@ -76,15 +97,32 @@ impl<'a> ReadBuf<'a> {
     pub fn from(slice: &'a [u8]) -> Self {
         ReadBuf {
             offset: 0,
+            last_read: 0,
             data: slice,
         }
     }
 
+    /// Number of bytes consumed so far
+    pub fn position(&self) -> usize {
+        self.offset
+    }
+
+    /// Attach to the error the offset at which the latest read started,
+    /// which is the read that failed or that returned the invalid data.
+    /// Errors that already have a position are left as they are.
+    pub fn locate_error(&self, e: ReadError) -> ReadError {
+        match e {
+            ReadError::AtPosition(_, _) => e,
+            e => ReadError::AtPosition(self.last_read, Box::new(e)),
+        }
+    }
+
     fn left(&self) -> usize {
         self.data.len() - self.offset
     }
 
-    fn assure_size(&self, expected: usize) -> Result<(), ReadError> {
+    fn assure_size(&mut self, expected: usize) -> Result<(), ReadError> {
+        self.last_read = self.offset;
         let left = self.left();
         if left >= expected {
             Ok(())
@@ -2,6 +2,7 @@ use crate::key::{AsymmetricKey, PublicKeyError, SecretKeyError};
 use crate::sign::{SignatureError, SigningAlgorithm, Verification, VerificationAlgorithm};
 use cryptoxide::ed25519;
 use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
 
 use ed25519_bip32::XPub;
 
@@ -11,6 +12,12 @@ pub struct Ed25519;
 #[derive(Clone)]
 pub struct Priv([u8; ed25519::SEED_LENGTH]);
 
+impl Drop for Priv {
+    fn drop(&mut self) {
+        self.0.zeroize()
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Pub(pub(crate) [u8; ed25519::PUBLIC_KEY_LENGTH]);
 
@@ -5,6 +5,7 @@ use super::ed25519 as ei;
 
 use cryptoxide::ed25519;
 use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
 
 use ed25519_bip32::{XPrv, XPRV_SIZE};
 
@@ -14,6 +15,12 @@ pub struct Ed25519Extended;
 #[derive(Clone)]
 pub struct ExtendedPriv([u8; ed25519::PRIVATE_KEY_LENGTH]);
 
+impl Drop for ExtendedPriv {
+    fn drop(&mut self) {
+        self.0.zeroize()
+    }
+}
+
 impl AsRef<[u8]> for ExtendedPriv {
     fn as_ref(&self) -> &[u8] {
         &self.0[..]
@@ -3,6 +3,7 @@ use crate::key::{AsymmetricKey, PublicKeyError, SecretKeyError};
 use crate::sign::{SignatureError, Verification, VerificationAlgorithm};
 use cryptoxide::ed25519;
 use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
 
 /// Fake MMM Signing Algorithm
 pub struct FakeMMM;
@@ -10,6 +11,12 @@ pub struct FakeMMM;
 #[derive(Clone)]
 pub struct Priv([u8; ed25519::SEED_LENGTH]);
 
+impl Drop for Priv {
+    fn drop(&mut self) {
+        self.0.zeroize()
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Pub([u8; ed25519::PUBLIC_KEY_LENGTH]);
 
@@ -32,6 +32,23 @@ pub fn try_from_bech32_to_bytes<B: Bech32>(bech32_str: &str) -> Result<Vec<u8>>
     Vec::<u8>::from_base32(bech32.data()).map_err(Into::into)
 }
 
+/// Same as `to_bech32_from_bytes`, with a human readable part chosen by
+/// the caller instead of the one of a `Bech32` type. Panics if `hrp` is
+/// not a valid human readable part.
+pub fn to_bech32_from_bytes_with_hrp(hrp: &str, bytes: &[u8]) -> String {
+    Bech32Data::new(hrp.to_string(), bytes.to_base32())
+        .unwrap_or_else(|e| panic!("Failed to build bech32: {}", e))
+        .to_string()
+}
+
+/// Decode a bech32 string whatever its human readable part, which is
+/// returned along with the data.
+pub fn try_from_bech32_to_hrp_and_bytes(bech32_str: &str) -> Result<(String, Vec<u8>)> {
+    let bech32: Bech32Data = bech32_str.parse()?;
+    let bytes = Vec::<u8>::from_base32(bech32.data())?;
+    Ok((bech32.hrp().to_string(), bytes))
+}
+
 #[derive(Debug)]
 pub enum Error {
     Bech32Malformed(Bech32Error),
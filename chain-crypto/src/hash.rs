@@ -2,6 +2,7 @@
 //! the common interfaces for the project to work with.
 
 use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
 use std::str::FromStr;
 use std::{error, fmt, result};
 
@@ -46,16 +47,42 @@ impl From<hex::DecodeError> for Error {
 
 pub type Result<T> = result::Result<T, Error>;
 
+/// incremental blake2b hashing, for inputs that are not in one
+/// contiguous slice
+pub struct Blake2bHasher<H> {
+    b2b: Blake2b,
+    phantom: PhantomData<H>,
+}
+
+impl<H> Blake2bHasher<H> {
+    pub fn update(&mut self, buf: &[u8]) -> &mut Self {
+        self.b2b.input(buf);
+        self
+    }
+}
+
 /// defines a blake2b object
 macro_rules! define_blake2b_new {
     ($hash_ty:ty) => {
         impl $hash_ty {
             pub fn new(buf: &[u8]) -> Self {
-                let mut b2b = Blake2b::new(Self::HASH_SIZE);
-                let mut out = [0; Self::HASH_SIZE];
-                b2b.input(buf);
-                b2b.result(&mut out);
-                Self::from(out)
+                let mut hasher = Self::hasher();
+                hasher.update(buf);
+                hasher.finalize()
+            }
+
+            pub fn hasher() -> Blake2bHasher<Self> {
+                Blake2bHasher {
+                    b2b: Blake2b::new(Self::HASH_SIZE),
+                    phantom: PhantomData,
+                }
+            }
+        }
+        impl Blake2bHasher<$hash_ty> {
+            pub fn finalize(mut self) -> $hash_ty {
+                let mut out = [0; <$hash_ty>::HASH_SIZE];
+                self.b2b.result(&mut out);
+                <$hash_ty>::from(out)
             }
         }
     };
@@ -78,6 +105,12 @@ macro_rules! define_hash_object {
                 buf[0..Self::HASH_SIZE].clone_from_slice(slice);
                 Ok(Self::from(buf))
             }
+
+            /// Compare two hashes in a time that does not depend on their
+            /// content.
+            pub fn constant_time_eq(&self, other: &Self) -> bool {
+                cryptoxide::util::fixed_time_eq(&self.0, &other.0)
+            }
         }
         impl AsRef<[u8]> for $hash_ty {
             fn as_ref(&self) -> &[u8] {
@@ -173,4 +206,14 @@ mod test {
             "Blake2b224(0x317512db8239e1f9c2549b04e8071f965983c938d3e649cec78532c7)",
         );
     }
+
+    #[test]
+    fn blake2b_256_hasher_chunked() {
+        let data: Vec<u8> = (0..200).collect();
+        let mut hasher = Blake2b256::hasher();
+        for chunk in data.chunks(7) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finalize(), Blake2b256::new(&data));
+    }
 }
@@ -62,6 +62,12 @@ impl<A: AsymmetricKey> std::fmt::Display for KeyPair<A> {
     }
 }
 
+impl<A: AsymmetricKey> fmt::Debug for SecretKey<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretKey(<redacted>)")
+    }
+}
+
 impl<A: AsymmetricKey> fmt::Debug for PublicKey<A> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", hex::encode(self.0.as_ref()))
@@ -120,6 +126,12 @@ impl<A: AsymmetricKey> PublicKey<A> {
     pub fn from_binary(data: &[u8]) -> Result<Self, PublicKeyError> {
         Ok(PublicKey(<A as AsymmetricKey>::public_from_binary(data)?))
     }
+
+    /// Compare two public keys in a time that does not depend on their
+    /// content, for comparisons that gate authorization decisions.
+    pub fn constant_time_eq(&self, other: &Self) -> bool {
+        cryptoxide::util::fixed_time_eq(self.0.as_ref(), other.0.as_ref())
+    }
 }
 
 impl<A: AsymmetricKey> Clone for SecretKey<A> {
@@ -192,18 +204,3 @@ impl<A: AsymmetricKey> Bech32 for SecretKey<A> {
         bech32::to_bech32_from_bytes::<Self>(self.0.as_ref())
     }
 }
-
-#[cfg(test)]
-mod test {
-    use super::*;
-
-    // ONLY ALLOWED WHEN TESTING
-    impl<A> std::fmt::Debug for SecretKey<A>
-    where
-        A: AsymmetricKey,
-    {
-        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-            write!(f, "SecretKey ({:?})", self.0.as_ref())
-        }
-    }
-}
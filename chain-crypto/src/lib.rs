@@ -13,9 +13,9 @@ extern crate quickcheck;
 
 cfg_if! {
     if #[cfg(test)] {
-        mod testing;
+        pub mod testing;
     } else if #[cfg(feature = "property-test-api")] {
-        mod testing;
+        pub mod testing;
     }
 }
 
@@ -30,11 +30,14 @@ mod vrf;
 
 pub use kes::KeyEvolvingSignatureAlgorithm;
 pub use key::{AsymmetricKey, KeyPair, PublicKey, PublicKeyError, SecretKey, SecretKeyError};
-pub use sign::{Signature, SignatureError, SigningAlgorithm, Verification, VerificationAlgorithm};
+pub use sign::{
+    Signature, SignatureError, SigningAlgorithm, Verification, VerificationAlgorithm,
+    VerificationError,
+};
 pub use vrf::{
     vrf_evaluate_and_proove, vrf_verified_get_output, vrf_verify, VRFVerification,
     VerifiableRandomFunction,
 };
 
 pub use algorithms::*;
-pub use hash::{Blake2b224, Blake2b256, Sha3_256};
+pub use hash::{Blake2b224, Blake2b256, Blake2bHasher, Sha3_256};
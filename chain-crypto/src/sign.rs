@@ -9,6 +9,32 @@ pub enum Verification {
     Success,
 }
 
+/// Error for a failed `Verification`, see `Verification::into_result`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerificationError;
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Verification failed")
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+impl Verification {
+    pub fn is_success(self) -> bool {
+        self == Verification::Success
+    }
+
+    /// Turn the verification into a `Result`, to compose with `?`
+    pub fn into_result(self) -> Result<(), VerificationError> {
+        match self {
+            Verification::Success => Ok(()),
+            Verification::Failed => Err(VerificationError),
+        }
+    }
+}
+
 impl From<bool> for Verification {
     fn from(b: bool) -> Self {
         if b {
@@ -163,4 +189,12 @@ pub(crate) mod test {
         let signature = Signature::generate(&sk, &data);
         signature.verify(&pk_random, &data) == Verification::Failed
     }
+
+    #[test]
+    fn verification_into_result() {
+        assert!(Verification::Success.is_success());
+        assert!(!Verification::Failed.is_success());
+        assert_eq!(Verification::Success.into_result(), Ok(()));
+        assert_eq!(Verification::Failed.into_result(), Err(VerificationError));
+    }
 }
@@ -17,6 +17,34 @@ where
     SecretKey::generate(rng)
 }
 
+/// Generate a keypair deterministically from the given seed, so tests
+/// can pin exact keys.
+pub fn keypair_from_seed<A: AsymmetricKey>(seed: u64) -> KeyPair<A> {
+    let rng = ChaChaRng::seed_from_u64(seed);
+    KeyPair::from(SecretKey::generate(rng))
+}
+
+/// Sign arbitrary data with an arbitrary key and check the signature
+/// verifies, then flip one byte of the data and check it no longer does.
+pub fn check_sign_verify<A, G>(g: &mut G)
+where
+    A: SigningAlgorithm,
+    G: Gen,
+{
+    let (secret_key, public_key) = KeyPair::<A>::from(arbitrary_secret_key(g)).into_keys();
+    let mut data: Vec<u8> = Arbitrary::arbitrary(g);
+    if data.is_empty() {
+        data.push(u8::arbitrary(g));
+    }
+
+    let signature = Signature::generate(&secret_key, &data);
+    assert_eq!(signature.verify(&public_key, &data), Verification::Success);
+
+    let index = usize::arbitrary(g) % data.len();
+    data[index] ^= 0x01;
+    assert_eq!(signature.verify(&public_key, &data), Verification::Failed);
+}
+
 impl<A> Arbitrary for PublicKey<A>
 where
     A: AsymmetricKey + 'static,
@@ -47,42 +75,138 @@ where
     }
 }
 
+/// Generate a valid signature, made with an arbitrary secret key, over
+/// random data. The signature is not related to any value of type `T`.
+///
+/// Only available for `SigningAlgorithm`s: key evolving algorithms such
+/// as `FakeMMM` only sign through `KeyEvolvingSignatureAlgorithm`, use
+/// `arbitrary_kes_signature` for them.
 impl<T, A> Arbitrary for Signature<T, A>
 where
-    A: VerificationAlgorithm + 'static,
+    A: SigningAlgorithm + 'static,
     A::Signature: Send,
     T: Send + 'static,
 {
     fn arbitrary<G: Gen>(g: &mut G) -> Self {
-        let bytes: Vec<_> = std::iter::repeat_with(|| u8::arbitrary(g))
-            .take(A::SIGNATURE_SIZE)
-            .collect();
-        Signature::from_binary(&bytes).unwrap()
+        let secret_key: SecretKey<A> = arbitrary_secret_key(g);
+        let data: Vec<u8> = Arbitrary::arbitrary(g);
+        Signature::generate(&secret_key, &data).coerce()
     }
 }
 
+/// Same as the `Arbitrary` instance of `Signature`, for the key evolving
+/// signature algorithms: the signature is made with an arbitrary secret
+/// key, which is evolved once in the process.
+pub fn arbitrary_kes_signature<T, A, G>(g: &mut G) -> Signature<T, A>
+where
+    A: KeyEvolvingSignatureAlgorithm,
+    G: Gen,
+{
+    let mut secret_key: SecretKey<A> = arbitrary_secret_key(g);
+    let data: Vec<u8> = Arbitrary::arbitrary(g);
+    Signature::generate_update(&mut secret_key, &data)
+}
+
+fn arbitrary_bytes<G: Gen>(g: &mut G, size: usize) -> Vec<u8> {
+    std::iter::repeat_with(|| u8::arbitrary(g))
+        .take(size)
+        .collect()
+}
+
 impl Arbitrary for Blake2b224 {
     fn arbitrary<G: Gen>(g: &mut G) -> Self {
-        let bytes: Vec<_> = std::iter::repeat_with(|| u8::arbitrary(g))
-            .take(Self::HASH_SIZE)
-            .collect();
-        Self::try_from_slice(&bytes).unwrap()
+        Self::try_from_slice(&arbitrary_bytes(g, Self::HASH_SIZE)).unwrap()
     }
 }
 impl Arbitrary for Blake2b256 {
     fn arbitrary<G: Gen>(g: &mut G) -> Self {
-        let bytes: Vec<_> = std::iter::repeat_with(|| u8::arbitrary(g))
-            .take(Self::HASH_SIZE)
-            .collect();
-        Self::try_from_slice(&bytes).unwrap()
+        Self::try_from_slice(&arbitrary_bytes(g, Self::HASH_SIZE)).unwrap()
     }
 }
 
 impl Arbitrary for Sha3_256 {
     fn arbitrary<G: Gen>(g: &mut G) -> Self {
-        let bytes: Vec<_> = std::iter::repeat_with(|| u8::arbitrary(g))
-            .take(Self::HASH_SIZE)
-            .collect();
-        Self::try_from_slice(&bytes).unwrap()
+        Self::try_from_slice(&arbitrary_bytes(g, Self::HASH_SIZE)).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn signature_bijection<A: VerificationAlgorithm>(signature: Signature<(), A>) -> bool {
+        match Signature::<(), A>::from_binary(signature.as_ref()) {
+            Ok(decoded) => decoded.as_ref() == signature.as_ref(),
+            Err(_) => false,
+        }
+    }
+
+    quickcheck! {
+        fn public_key_constant_time_eq(pk1: PublicKey<Ed25519>, pk2: PublicKey<Ed25519>) -> bool {
+            pk1.constant_time_eq(&pk1)
+                && pk1.constant_time_eq(&pk2) == (pk1 == pk2)
+        }
+        fn blake2b256_constant_time_eq(h1: Blake2b256, h2: Blake2b256) -> bool {
+            h1.constant_time_eq(&h1) && h1.constant_time_eq(&h2) == (h1 == h2)
+        }
+        fn blake2b224_bijection(hash: Blake2b224) -> bool {
+            Blake2b224::try_from_slice(hash.as_ref()) == Ok(hash)
+                && hash.to_string().parse() == Ok(hash)
+        }
+        fn blake2b256_bijection(hash: Blake2b256) -> bool {
+            Blake2b256::try_from_slice(hash.as_ref()) == Ok(hash)
+                && hash.to_string().parse() == Ok(hash)
+        }
+        fn sha3_256_bijection(hash: Sha3_256) -> bool {
+            Sha3_256::try_from_slice(hash.as_ref()) == Ok(hash)
+                && hash.to_string().parse() == Ok(hash)
+        }
+        fn ed25519_signature_bijection(signature: Signature<(), Ed25519>) -> bool {
+            signature_bijection(signature)
+        }
+        fn ed25519_extended_signature_bijection(signature: Signature<(), Ed25519Extended>) -> bool {
+            signature_bijection(signature)
+        }
+        fn ed25519_bip32_signature_bijection(signature: Signature<(), Ed25519Bip32>) -> bool {
+            signature_bijection(signature)
+        }
+    }
+
+    fn sign_verify<A: SigningAlgorithm>() {
+        let mut g = quickcheck::StdThreadGen::new(100);
+        for _ in 0..100 {
+            check_sign_verify::<A, _>(&mut g);
+        }
+    }
+
+    #[test]
+    fn ed25519_sign_verify() {
+        sign_verify::<Ed25519>()
+    }
+
+    #[test]
+    fn ed25519_extended_sign_verify() {
+        sign_verify::<Ed25519Extended>()
+    }
+
+    #[test]
+    fn ed25519_bip32_sign_verify() {
+        sign_verify::<Ed25519Bip32>()
+    }
+
+    #[test]
+    fn fakemmm_arbitrary_kes_signature() {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let signature: Signature<(), FakeMMM> = arbitrary_kes_signature(&mut g);
+        assert!(signature_bijection(signature));
+    }
+
+    #[test]
+    fn keypair_from_seed_is_deterministic() {
+        let kp1 = keypair_from_seed::<Ed25519>(42);
+        let kp2 = keypair_from_seed::<Ed25519>(42);
+        let kp3 = keypair_from_seed::<Ed25519>(43);
+        assert_eq!(kp1.public_key(), kp2.public_key());
+        assert_ne!(kp1.public_key(), kp3.public_key());
     }
 }
@@ -1,5 +1,7 @@
-use crate::key;
+use crate::key::{self, AccountPublicKey, AccountSecretKey, Hash};
 use crate::value::*;
+use chain_core::mempack::{ReadBuf, ReadError, Readable};
+use chain_core::property;
 use chain_crypto::{Ed25519Extended, PublicKey};
 use imhamt::{Hamt, InsertError, UpdateError};
 use std::collections::hash_map::DefaultHasher;
@@ -59,6 +61,35 @@ impl From<Identifier> for PublicKey<AccountAlg> {
 /// Account Secret Key
 pub type Secret = key::AccountSecretKey;
 
+/// Stable identifier of an account, the hash of its public key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AccountId(Hash);
+
+impl From<&AccountPublicKey> for AccountId {
+    fn from(key: &AccountPublicKey) -> Self {
+        AccountId(Hash::hash_bytes(key.as_ref()))
+    }
+}
+
+impl From<&AccountSecretKey> for AccountId {
+    fn from(key: &AccountSecretKey) -> Self {
+        AccountId::from(&key.to_public())
+    }
+}
+
+impl property::Serialize for AccountId {
+    type Error = std::io::Error;
+    fn serialize<W: std::io::Write>(&self, writer: W) -> Result<(), Self::Error> {
+        self.0.serialize(writer)
+    }
+}
+
+impl Readable for AccountId {
+    fn read<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+        Hash::read(buf).map(AccountId)
+    }
+}
+
 #[derive(Clone)]
 pub struct State {
     counter: SpendingCounter,
@@ -93,14 +124,14 @@ impl State {
     pub fn sub(&self, v: Value) -> Result<Option<State>, LedgerError> {
         let new_value = (self.value - v)?;
         match self.counter.increment() {
-            None => {
+            Err(SpendingCounterError::Overflow) => {
                 if new_value == Value::zero() {
                     Ok(None)
                 } else {
                     Err(LedgerError::NeedTotalWithdrawal)
                 }
             }
-            Some(new_counter) => Ok(Some(State {
+            Ok(new_counter) => Ok(Some(State {
                 counter: new_counter,
                 value: new_value,
             })),
@@ -121,12 +152,43 @@ impl State {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SpendingCounter(u32);
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpendingCounterError {
+    /// the counter is already at its maximum value
+    Overflow,
+}
+
+impl std::fmt::Display for SpendingCounterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SpendingCounterError::Overflow => write!(f, "spending counter overflow"),
+        }
+    }
+}
+
+impl std::error::Error for SpendingCounterError {}
+
 impl SpendingCounter {
     pub fn zero() -> Self {
         SpendingCounter(0)
     }
-    fn increment(&self) -> Option<Self> {
-        self.0.checked_add(1).map(SpendingCounter)
+
+    /// the next counter value, or an error if the counter is
+    /// already at its maximum
+    pub fn increment(self) -> Result<Self, SpendingCounterError> {
+        self.0
+            .checked_add(1)
+            .map(SpendingCounter)
+            .ok_or(SpendingCounterError::Overflow)
+    }
+
+    /// the next counter value
+    ///
+    /// # Panics
+    ///
+    /// panics if the counter is already at its maximum
+    pub fn increment_unchecked(self) -> Self {
+        self.increment().unwrap()
     }
 
     pub fn to_bytes(&self) -> [u8; 4] {
@@ -223,3 +285,60 @@ impl Ledger {
         Value::sum(values)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chain_crypto::{KeyPair, SecretKey};
+    use quickcheck::{Arbitrary, Gen, TestResult};
+
+    impl Arbitrary for AccountId {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            AccountId(Arbitrary::arbitrary(g))
+        }
+    }
+
+    quickcheck! {
+        fn account_id_serialization_bijection(id: AccountId) -> TestResult {
+            property::testing::serialization_bijection_r(id)
+        }
+
+        fn account_id_same_from_secret_and_public(key: KeyPair<Ed25519Extended>) -> bool {
+            AccountId::from(key.private_key()) == AccountId::from(key.public_key())
+        }
+    }
+
+    #[test]
+    fn account_id_differs_between_keys() {
+        use rand_chacha::ChaChaRng;
+        use rand_core::SeedableRng;
+
+        let mut rng = ChaChaRng::from_seed([0; 32]);
+        let sk1 = SecretKey::<Ed25519Extended>::generate(&mut rng);
+        let sk2 = SecretKey::<Ed25519Extended>::generate(&mut rng);
+        assert_ne!(AccountId::from(&sk1), AccountId::from(&sk2));
+    }
+
+    #[test]
+    fn spending_counter_increment() {
+        assert_eq!(SpendingCounter::zero().increment(), Ok(SpendingCounter(1)));
+        assert_eq!(
+            SpendingCounter(41).increment_unchecked(),
+            SpendingCounter(42)
+        );
+    }
+
+    #[test]
+    fn spending_counter_increment_overflow() {
+        assert_eq!(
+            SpendingCounter(u32::MAX).increment(),
+            Err(SpendingCounterError::Overflow)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn spending_counter_increment_unchecked_overflow() {
+        SpendingCounter(u32::MAX).increment_unchecked();
+    }
+}
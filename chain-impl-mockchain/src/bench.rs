@@ -0,0 +1,24 @@
+use crate::transaction::*;
+use chain_addr::Address;
+use quickcheck::{Arbitrary, StdThreadGen};
+
+fn new_transaction() -> AuthenticatedTransaction<Address, NoExtra> {
+    let mut g = StdThreadGen::new(10);
+    AuthenticatedTransaction::arbitrary(&mut g)
+}
+
+#[bench]
+fn transaction_id_rehash(b: &mut test::Bencher) {
+    let tx = new_transaction();
+    b.iter(|| {
+        let _ = tx.transaction.hash();
+    })
+}
+
+#[bench]
+fn transaction_id_cached(b: &mut test::Bencher) {
+    let tx = IdentifiedTransaction::new(new_transaction());
+    b.iter(|| {
+        let _ = tx.id();
+    })
+}
@@ -2,11 +2,10 @@
 //!
 
 use crate::block::{
-    BftProof, Block, BlockContentHash, BlockContents, BlockDate, BlockId, BlockVersion,
-    ChainLength, Common, GenesisPraosProof, Header, KESSignature, Message, Proof,
+    sign_bft, Block, BlockContentHash, BlockContents, BlockDate, BlockId, BlockVersion,
+    ChainLength, Common, ConsensusVersion, GenesisPraosProof, Header, KESSignature, Message, Proof,
 };
-use crate::key::{make_signature, make_signature_update};
-use crate::leadership;
+use crate::key::{make_signature_update, Hash, Hasher};
 use crate::stake;
 use crate::transaction::{AuthenticatedTransaction, NoExtra};
 use chain_addr::Address;
@@ -14,17 +13,57 @@ use chain_crypto::{
     Curve25519_2HashDH, Ed25519Extended, FakeMMM, SecretKey, VerifiableRandomFunction,
 };
 
+/// error when the block to build does not match the chain's settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockBuilderError {
+    /// the block would carry a proof of the `proof` consensus on a
+    /// chain running the `expected` one
+    ConsensusMismatch {
+        expected: ConsensusVersion,
+        proof: ConsensusVersion,
+    },
+}
+
+impl std::fmt::Display for BlockBuilderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BlockBuilderError::ConsensusMismatch { expected, proof } => write!(
+                f,
+                "Cannot make a {} block on a chain running the {} consensus",
+                proof, expected
+            ),
+        }
+    }
+}
+impl std::error::Error for BlockBuilderError {}
+
+fn check_consensus(
+    expected: ConsensusVersion,
+    proof: ConsensusVersion,
+) -> Result<(), BlockBuilderError> {
+    if expected == proof {
+        Ok(())
+    } else {
+        Err(BlockBuilderError::ConsensusMismatch { expected, proof })
+    }
+}
+
 pub struct BlockBuilder {
     pub common: Common,
-    pub contents: BlockContents,
+    contents: BlockContents,
+    /// hash and size of the contents, updated as the messages are added
+    content_hasher: Hasher,
+    content_size: usize,
 }
 
 impl From<Block> for BlockBuilder {
     fn from(block: Block) -> BlockBuilder {
-        BlockBuilder {
+        let mut builder = BlockBuilder {
             common: block.header.common,
-            contents: block.contents,
-        }
+            ..BlockBuilder::new()
+        };
+        builder.messages(block.contents.0);
+        builder
     }
 }
 
@@ -43,9 +82,16 @@ impl BlockBuilder {
                 chain_length: ChainLength(0),
             },
             contents: BlockContents::new(Vec::new()),
+            content_hasher: Hash::hasher(),
+            content_size: 0,
         }
     }
 
+    /// the messages added so far
+    pub fn contents(&self) -> &BlockContents {
+        &self.contents
+    }
+
     /// set the block date
     pub fn date(&mut self, block_date: BlockDate) -> &mut Self {
         self.common.block_date = block_date;
@@ -76,6 +122,11 @@ impl BlockBuilder {
 
     /// add a message in the block to build
     pub fn message(&mut self, message: Message) -> &mut Self {
+        use chain_core::property::Serialize;
+        let mut bytes = Vec::new();
+        message.to_raw().serialize(&mut bytes).unwrap();
+        self.content_hasher.update(&bytes);
+        self.content_size += bytes.len();
         self.contents.0.push(message);
         self
     }
@@ -85,7 +136,9 @@ impl BlockBuilder {
     where
         I: IntoIterator<Item = Message>,
     {
-        self.contents.0.extend(messages);
+        for message in messages {
+            self.message(message);
+        }
         self
     }
 
@@ -100,9 +153,8 @@ impl BlockBuilder {
     }
 
     fn finalize_common(&mut self, block_version: BlockVersion) -> &mut Self {
-        let (content_hash, content_size) = self.contents.compute_hash_size();
-        self.common.block_content_hash = content_hash;
-        self.common.block_content_size = content_size as u32;
+        self.common.block_content_hash = self.content_hasher.finalize();
+        self.common.block_content_size = self.content_size as u32;
         self.common.any_block_version = block_version.into();
         self
     }
@@ -124,13 +176,21 @@ impl BlockBuilder {
     pub fn make_bft_block(mut self, bft_signing_key: &SecretKey<Ed25519Extended>) -> Block {
         assert_ne!(self.common.chain_length, ChainLength(0));
         self.finalize_common(BlockVersion::Ed25519Signed);
-        let bft_proof = BftProof {
-            leader_id: leadership::bft::LeaderId(bft_signing_key.to_public()),
-            signature: super::BftSignature(make_signature(bft_signing_key, &self.common)),
-        };
+        let bft_proof = sign_bft(&self.common, bft_signing_key);
         self.make_block(Proof::Bft(bft_proof))
     }
 
+    /// same as `make_bft_block`, failing if the chain runs another
+    /// consensus than BFT
+    pub fn make_bft_block_for(
+        self,
+        consensus_version: ConsensusVersion,
+        bft_signing_key: &SecretKey<Ed25519Extended>,
+    ) -> Result<Block, BlockBuilderError> {
+        check_consensus(consensus_version, ConsensusVersion::Bft)?;
+        Ok(self.make_bft_block(bft_signing_key))
+    }
+
     /// create a Praos/Genesis block, this block will be signed with the
     /// given KES key.
     pub fn make_genesis_praos_block(
@@ -150,4 +210,16 @@ impl BlockBuilder {
         };
         self.make_block(Proof::GenesisPraos(genesis_praos_proof))
     }
+    /// same as `make_genesis_praos_block`, failing if the chain runs
+    /// another consensus than Genesis Praos
+    pub fn make_genesis_praos_block_for(
+        self,
+        consensus_version: ConsensusVersion,
+        node_id: &stake::StakePoolId,
+        kes_signing_key: &mut SecretKey<FakeMMM>,
+        vrf_proof: <Curve25519_2HashDH as VerifiableRandomFunction>::VerifiedRandomOutput,
+    ) -> Result<Block, BlockBuilderError> {
+        check_consensus(consensus_version, ConsensusVersion::GenesisPraos)?;
+        Ok(self.make_genesis_praos_block(node_id, kes_signing_key, vrf_proof))
+    }
 }
@@ -1,19 +1,24 @@
 use crate::block::{
     headerraw::HeaderRaw,
-    version::{AnyBlockVersion, BlockVersion},
+    version::{AnyBlockVersion, BlockVersion, ConsensusVersion},
 };
 use crate::date::BlockDate;
 use crate::key::{
-    deserialize_public_key, deserialize_signature, serialize_public_key, serialize_signature, Hash,
+    deserialize_public_key_with_label, deserialize_signature_with_label, make_signature,
+    serialize_public_key, serialize_signature, verify_signature, Hash,
+};
+use crate::leadership::{
+    bft,
+    genesis::{self, GenesisPraosLeader},
 };
-use crate::leadership::bft;
 use crate::stake::StakePoolId;
 use chain_core::{
     mempack::{read_from_raw, ReadBuf, ReadError, Readable},
     property,
 };
 use chain_crypto::{
-    self, Curve25519_2HashDH, Ed25519Extended, FakeMMM, Signature, VerifiableRandomFunction,
+    self, AsymmetricKey, Curve25519_2HashDH, Ed25519Extended, FakeMMM, Signature, VRFVerification,
+    VerifiableRandomFunction, Verification, VerificationAlgorithm,
 };
 
 pub type HeaderHash = Hash;
@@ -65,6 +70,40 @@ pub enum Proof {
     GenesisPraos(GenesisPraosProof),
 }
 
+impl Proof {
+    /// Number of bytes the proof adds to the serialized header
+    pub fn serialized_size(&self) -> usize {
+        match self {
+            Proof::None => 0,
+            Proof::Bft(_) => {
+                <Ed25519Extended as AsymmetricKey>::PUBLIC_KEY_SIZE
+                    + <Ed25519Extended as VerificationAlgorithm>::SIGNATURE_SIZE
+            }
+            Proof::GenesisPraos(_) => {
+                chain_crypto::Blake2b256::HASH_SIZE
+                    + <Curve25519_2HashDH as VerifiableRandomFunction>::VERIFIED_RANDOM_SIZE
+                    + <FakeMMM as VerificationAlgorithm>::SIGNATURE_SIZE
+            }
+        }
+    }
+
+    /// the BFT proof, `None` for the other kinds of proof
+    pub fn as_bft(&self) -> Option<&BftProof> {
+        match self {
+            Proof::Bft(proof) => Some(proof),
+            _ => None,
+        }
+    }
+
+    /// the Genesis Praos proof, `None` for the other kinds of proof
+    pub fn as_genesis_praos(&self) -> Option<&GenesisPraosProof> {
+        match self {
+            Proof::GenesisPraos(proof) => Some(proof),
+            _ => None,
+        }
+    }
+}
+
 /// this is the block header, it contains the necessary data
 /// to prove a given block has been signed by the appropriate
 /// nodes, it also contains the metadata to localize the block
@@ -82,6 +121,15 @@ impl PartialEq<Self> for BftSignature {
 }
 impl Eq for BftSignature {}
 
+/// Sign the common fields of a header with a BFT leader key, the proof
+/// verifies against the leader id of the key.
+pub fn sign_bft(common: &Common, key: &bft::SigningKey) -> BftProof {
+    BftProof {
+        leader_id: bft::LeaderId(key.to_public()),
+        signature: BftSignature(make_signature(key, common)),
+    }
+}
+
 impl PartialEq<Self> for KESSignature {
     fn eq(&self, other: &Self) -> bool {
         self.0.as_ref() == other.0.as_ref()
@@ -89,13 +137,50 @@ impl PartialEq<Self> for KESSignature {
 }
 impl Eq for KESSignature {}
 
+impl ChainLength {
+    /// the chain length of the next block, `None` if this is the maximum
+    /// chain length
+    pub fn next(self) -> Option<ChainLength> {
+        self.0.checked_add(1).map(ChainLength)
+    }
+
+    /// number of blocks between two chain lengths, in either order
+    pub fn distance(self, other: ChainLength) -> u32 {
+        std::cmp::max(self.0, other.0) - std::cmp::min(self.0, other.0)
+    }
+
+    /// number of blocks this chain length is ahead of `other`, or `None`
+    /// if it is behind
+    pub fn checked_sub(self, other: ChainLength) -> Option<u32> {
+        self.0.checked_sub(other.0)
+    }
+
+    /// signed number of blocks between the two chain lengths: positive
+    /// when this chain length is ahead of `other`, negative when behind
+    pub fn diff(self, other: ChainLength) -> i64 {
+        i64::from(self.0) - i64::from(other.0)
+    }
+}
+
 impl std::fmt::Display for ChainLength {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{}", self.0)
     }
 }
 
+impl Common {
+    /// Number of bytes written by `property::Serialize`: the version, the
+    /// content size, the date, the chain length and the two hashes
+    pub const SERIALIZED_SIZE: usize = 2 + 4 + 4 + 4 + 4 + 32 + 32;
+}
+
 impl Header {
+    /// Number of bytes written by `property::Serialize`, without
+    /// serializing the header
+    pub fn serialized_size(&self) -> usize {
+        Common::SERIALIZED_SIZE + self.proof.serialized_size()
+    }
+
     #[inline]
     pub fn block_version(&self) -> AnyBlockVersion {
         self.common.any_block_version
@@ -129,20 +214,73 @@ impl Header {
     /// of the serialized header (except the first 2bytes: the size)
     #[inline]
     pub fn hash(&self) -> HeaderHash {
-        // TODO: this is not the optimal way to compute the hash
-        use chain_core::property::Serialize;
-        let bytes = self.serialize_as_vec().unwrap();
-        HeaderHash::hash_bytes(&bytes[..])
+        HeaderHash::hash_of(self).unwrap()
     }
 
     pub fn proof(&self) -> &Proof {
         &self.proof
     }
+
+    /// replace the proof of the header. The common fields are left as they
+    /// are, so the block version should match the consensus of the proof.
+    pub fn set_proof(&mut self, proof: Proof) {
+        self.proof = proof
+    }
+
+    /// the consensus that produced the block, according to its proof.
+    /// `None` for blocks without proof, such as the genesis block.
+    pub fn proof_kind(&self) -> Option<ConsensusVersion> {
+        match self.proof {
+            Proof::None => None,
+            Proof::Bft(_) => Some(ConsensusVersion::Bft),
+            Proof::GenesisPraos(_) => Some(ConsensusVersion::GenesisPraos),
+        }
+    }
+
+    /// check the header has been signed by one of the given BFT leaders.
+    /// Headers without a BFT proof always fail the verification.
+    pub fn verify_bft_proof(&self, leaders: &[bft::LeaderId]) -> Verification {
+        match &self.proof {
+            Proof::Bft(proof) if leaders.contains(&proof.leader_id) => {
+                verify_signature(&proof.signature.0, &proof.leader_id.0, &self.common)
+            }
+            _ => Verification::Failed,
+        }
+    }
+
+    /// check the header has been produced by the given GenesisPraos leader:
+    /// the KES signature must cover the header content and the VRF proof
+    /// must be valid for the slot of the header under the epoch nonce.
+    /// Headers without a GenesisPraos proof always fail the verification.
+    pub fn verify_genesis_praos_proof(
+        &self,
+        leader: &GenesisPraosLeader,
+        epoch_nonce: &genesis::Nonce,
+    ) -> Verification {
+        let proof = match &self.proof {
+            Proof::GenesisPraos(proof) => proof,
+            _ => return Verification::Failed,
+        };
+        let vrf = genesis::vrfeval::verify_witness(
+            &leader.vrf_public_key,
+            epoch_nonce,
+            self.common.block_date.slot_id,
+            &proof.vrf_proof,
+        );
+        if vrf != VRFVerification::Success {
+            return Verification::Failed;
+        }
+        verify_signature(&proof.kes_proof.0, &leader.kes_public_key, &self.common)
+    }
 }
 
+/// # Panics
+///
+/// `next` panics on the maximum chain length, use the inherent
+/// `ChainLength::next` to handle it
 impl property::ChainLength for ChainLength {
     fn next(&self) -> Self {
-        ChainLength(self.0.checked_add(1).unwrap())
+        ChainLength::next(*self).expect("chain length overflow")
     }
 }
 
@@ -224,8 +362,10 @@ impl Readable for Header {
             AnyBlockVersion::Supported(BlockVersion::Genesis) => Proof::None,
             AnyBlockVersion::Supported(BlockVersion::Ed25519Signed) => {
                 // BFT
-                let leader_id = deserialize_public_key(buf).map(bft::LeaderId)?;
-                let signature = deserialize_signature(buf).map(BftSignature)?;
+                let leader_id =
+                    deserialize_public_key_with_label(buf, "bft leader id").map(bft::LeaderId)?;
+                let signature =
+                    deserialize_signature_with_label(buf, "bft signature").map(BftSignature)?;
                 Proof::Bft(BftProof {
                     leader_id,
                     signature,
@@ -241,7 +381,8 @@ impl Readable for Header {
                         .ok_or(ReadError::StructureInvalid("VRF Proof".to_string()))
                 }?;
                 dbg!(&vrf_proof);
-                let kes_proof = deserialize_signature(buf).map(KESSignature)?;
+                let kes_proof =
+                    deserialize_signature_with_label(buf, "kes signature").map(KESSignature)?;
                 dbg!(&kes_proof);
 
                 Proof::GenesisPraos(GenesisPraosProof {
@@ -293,15 +434,198 @@ impl property::Header for Header {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::block::ConsensusVersion;
-    use chain_crypto::AsymmetricKey;
+    use crate::key::make_signature_update;
+    use chain_crypto::{AsymmetricKey, KeyPair, PublicKey, SecretKey};
     use num_traits::FromPrimitive;
     use quickcheck::{Arbitrary, Gen, TestResult};
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+
+    #[test]
+    fn chain_length_next() {
+        assert_eq!(ChainLength(0).next(), Some(ChainLength(1)));
+        assert_eq!(
+            ChainLength(u32::MAX - 1).next(),
+            Some(ChainLength(u32::MAX))
+        );
+        assert_eq!(ChainLength(u32::MAX).next(), None);
+    }
+
+    #[test]
+    fn chain_length_diff() {
+        let (short, long) = (ChainLength(3), ChainLength(u32::MAX));
+        assert_eq!(short.diff(short), 0);
+        assert_eq!(short.checked_sub(short), Some(0));
+        assert_eq!(long.diff(short), i64::from(u32::MAX - 3));
+        assert_eq!(long.checked_sub(short), Some(u32::MAX - 3));
+        assert_eq!(short.diff(long), -i64::from(u32::MAX - 3));
+        assert_eq!(short.checked_sub(long), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn chain_length_property_next_overflow() {
+        property::ChainLength::next(&ChainLength(u32::MAX));
+    }
 
     quickcheck! {
+        fn chain_length_distance_symmetric(a: u32, b: u32) -> bool {
+            let (a, b) = (ChainLength(a), ChainLength(b));
+            a.distance(b) == b.distance(a) && a.distance(a) == 0
+        }
+
         fn header_serialization_bijection(b: Header) -> TestResult {
             property::testing::serialization_bijection_r(b)
         }
+
+        fn header_serialized_size(header: Header) -> bool {
+            use chain_core::property::Serialize;
+            header.serialized_size() == header.serialize_as_vec().unwrap().len()
+        }
+
+        fn header_proof_kind(common: Common, bft: BftProof, praos: GenesisPraosProof) -> bool {
+            let header = |proof| Header {
+                common: common.clone(),
+                proof,
+            };
+            header(Proof::None).proof_kind().is_none()
+                && header(Proof::Bft(bft)).proof_kind() == Some(ConsensusVersion::Bft)
+                && header(Proof::GenesisPraos(praos)).proof_kind()
+                    == Some(ConsensusVersion::GenesisPraos)
+        }
+
+        fn proof_accessors(bft: BftProof, praos: GenesisPraosProof) -> bool {
+            let none = Proof::None;
+            let bft_proof = Proof::Bft(bft.clone());
+            let praos_proof = Proof::GenesisPraos(praos.clone());
+
+            none.as_bft().is_none()
+                && none.as_genesis_praos().is_none()
+                && bft_proof.as_bft() == Some(&bft)
+                && bft_proof.as_genesis_praos().is_none()
+                && praos_proof.as_bft().is_none()
+                && praos_proof.as_genesis_praos() == Some(&praos)
+        }
+
+        fn header_verify_bft_proof(
+            common: Common,
+            leader: KeyPair<Ed25519Extended>,
+            others: Vec<bft::LeaderId>,
+            praos: GenesisPraosProof
+        ) -> TestResult {
+            let leader_id = bft::LeaderId(leader.public_key().clone());
+            if others.contains(&leader_id) {
+                return TestResult::discard();
+            }
+            let header = Header {
+                proof: Proof::Bft(BftProof {
+                    leader_id: leader_id.clone(),
+                    signature: BftSignature(make_signature(leader.private_key(), &common)),
+                }),
+                common: common.clone(),
+            };
+            let mut leaders = others.clone();
+            leaders.push(leader_id);
+
+            let mut tampered = header.clone();
+            tampered.common.chain_length = ChainLength(common.chain_length.0.wrapping_add(1));
+            let praos = Header {
+                common,
+                proof: Proof::GenesisPraos(praos),
+            };
+
+            TestResult::from_bool(
+                header.verify_bft_proof(&leaders) == Verification::Success
+                    && header.verify_bft_proof(&others) == Verification::Failed
+                    && tampered.verify_bft_proof(&leaders) == Verification::Failed
+                    && praos.verify_bft_proof(&leaders) == Verification::Failed
+            )
+        }
+
+        fn header_sign_bft_verifies(
+            common: Common,
+            leader: KeyPair<Ed25519Extended>,
+            other: bft::LeaderId
+        ) -> TestResult {
+            let leader_id = bft::LeaderId(leader.public_key().clone());
+            if other == leader_id {
+                return TestResult::discard();
+            }
+            let mut header = Header {
+                common: common.clone(),
+                proof: Proof::None,
+            };
+            header.set_proof(Proof::Bft(sign_bft(&common, leader.private_key())));
+
+            TestResult::from_bool(
+                header.verify_bft_proof(&[leader_id]) == Verification::Success
+                    && header.verify_bft_proof(&[other]) == Verification::Failed
+            )
+        }
+
+        fn header_verify_genesis_praos_proof(
+            common: Common,
+            kes_key: SecretKey<FakeMMM>,
+            vrf_key: SecretKey<Curve25519_2HashDH>,
+            other_kes: PublicKey<FakeMMM>,
+            other_vrf: PublicKey<Curve25519_2HashDH>,
+            node_id: StakePoolId,
+            seed: u64
+        ) -> TestResult {
+            let leader = GenesisPraosLeader {
+                kes_public_key: kes_key.to_public(),
+                vrf_public_key: vrf_key.to_public(),
+            };
+            if other_kes == leader.kes_public_key || other_vrf == leader.vrf_public_key {
+                return TestResult::discard();
+            }
+            let nonce = genesis::Nonce::zero();
+            let mut kes_key = kes_key;
+            let header = Header {
+                proof: Proof::GenesisPraos(GenesisPraosProof {
+                    node_id,
+                    vrf_proof: genesis::vrfeval::evaluate_witness(
+                        &vrf_key,
+                        &nonce,
+                        common.block_date.slot_id,
+                        ChaChaRng::seed_from_u64(seed),
+                    ),
+                    kes_proof: KESSignature(make_signature_update(&mut kes_key, &common)),
+                }),
+                common: common.clone(),
+            };
+            let wrong_kes = GenesisPraosLeader {
+                kes_public_key: other_kes,
+                vrf_public_key: leader.vrf_public_key.clone(),
+            };
+            let wrong_vrf = GenesisPraosLeader {
+                kes_public_key: leader.kes_public_key.clone(),
+                vrf_public_key: other_vrf,
+            };
+
+            let mut tampered = header.clone();
+            tampered.common.chain_length = ChainLength(common.chain_length.0.wrapping_add(1));
+            let unproven = Header {
+                common,
+                proof: Proof::None,
+            };
+
+            TestResult::from_bool(
+                header.verify_genesis_praos_proof(&leader, &nonce) == Verification::Success
+                    && header.verify_genesis_praos_proof(&wrong_kes, &nonce) == Verification::Failed
+                    && header.verify_genesis_praos_proof(&wrong_vrf, &nonce) == Verification::Failed
+                    && tampered.verify_genesis_praos_proof(&leader, &nonce) == Verification::Failed
+                    && unproven.verify_genesis_praos_proof(&leader, &nonce) == Verification::Failed
+            )
+        }
+
+        fn header_proof_kind_matches_block_version(header: Header) -> bool {
+            header.proof_kind()
+                == header
+                    .block_version()
+                    .try_into_block_version()
+                    .and_then(BlockVersion::get_consensus)
+        }
     }
 
     impl Arbitrary for AnyBlockVersion {
@@ -342,8 +666,6 @@ mod test {
     }
     impl Arbitrary for GenesisPraosProof {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
-            use rand_chacha::ChaChaRng;
-            use rand_core::SeedableRng;
             let mut seed = [0; 32];
             for byte in seed.iter_mut() {
                 *byte = Arbitrary::arbitrary(g);
@@ -1,8 +1,16 @@
 //! Representation of the block in the mockchain.
+use crate::certificate::CertificateContent;
+use crate::fee::{FeeAlgorithm, LinearFee};
 use crate::key::Hash;
-use crate::message::{Message, MessageRaw};
-use chain_core::mempack::read_from_raw;
+use crate::leadership::bft;
+use crate::message::{Message, MessageId, MessageRaw, ToMessageRaw};
+use crate::setting::{Settings, UpdateProposal};
+use crate::stake::StakePoolId;
+use crate::transaction::BalanceError;
+use crate::value::Value;
+use chain_core::mempack::{read_from_raw, ReadBuf, ReadError, Readable};
 use chain_core::property::{self, Serialize};
+use chain_crypto::Verification;
 
 mod builder;
 //mod cstruct;
@@ -12,11 +20,11 @@ mod version;
 
 pub use self::version::{AnyBlockVersion, BlockVersion, ConsensusVersion};
 
-pub use self::builder::BlockBuilder;
+pub use self::builder::{BlockBuilder, BlockBuilderError};
 
 pub use self::header::{
-    BftProof, BftSignature, BlockContentHash, BlockContentSize, BlockId, ChainLength, Common,
-    GenesisPraosProof, Header, HeaderHash, KESSignature, Proof,
+    sign_bft, BftProof, BftSignature, BlockContentHash, BlockContentSize, BlockId, ChainLength,
+    Common, GenesisPraosProof, Header, HeaderHash, KESSignature, Proof,
 };
 pub use self::headerraw::HeaderRaw;
 pub use self::version::*;
@@ -58,25 +66,452 @@ impl BlockContents {
     pub fn iter<'a>(&'a self) -> impl Iterator<Item = &'a Message> {
         self.0.iter()
     }
+    /// Identifiers of the messages, in order. Each identifier is the hash
+    /// of the serialized message without its size prefix, as for
+    /// `property::Message::id`.
+    pub fn message_ids<'a>(&'a self) -> impl Iterator<Item = MessageId> + 'a {
+        self.iter().map(|message| message.to_raw().id())
+    }
     pub fn compute_hash_size(&self) -> (BlockContentHash, usize) {
-        let mut bytes = Vec::with_capacity(4096);
+        compute_messages_hash_size(self.iter())
+    }
+}
+
+/// The hash and the size of the contents made of the given messages, in
+/// order. Each message is serialized with its `MessageRaw` size prefix,
+/// which is how the contents are hashed in the block header.
+pub fn compute_messages_hash_size<'a, M, I>(messages: I) -> (BlockContentHash, usize)
+where
+    M: ToMessageRaw + 'a,
+    I: IntoIterator<Item = &'a M>,
+{
+    let mut bytes = Vec::with_capacity(4096);
+
+    for message in messages {
+        message.to_raw().serialize(&mut bytes).unwrap();
+    }
+
+    let hash = Hash::hash_bytes(&bytes);
+    (hash, bytes.len())
+}
+
+impl std::iter::FromIterator<Message> for BlockContents {
+    fn from_iter<I: IntoIterator<Item = Message>>(iter: I) -> Self {
+        BlockContents(iter.into_iter().collect())
+    }
+}
+
+impl Extend<Message> for BlockContents {
+    fn extend<I: IntoIterator<Item = Message>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+/// error when a block does not fit the chain's settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockSettingsError {
+    /// the contents of the block are bigger than allowed
+    ContentSizeTooBig { size: u32, max: u32 },
+    /// the block holds more update proposals than allowed
+    TooManyUpdates { count: usize, max: u32 },
+    /// the block holds more certificates than allowed
+    TooManyCertificates { count: usize, max: u32 },
+}
 
-        for message in self.iter() {
-            message.to_raw().serialize(&mut bytes).unwrap();
+impl std::fmt::Display for BlockSettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BlockSettingsError::ContentSizeTooBig { size, max } => write!(
+                f,
+                "Block content size {} is bigger than the maximum of {} bytes",
+                size, max
+            ),
+            BlockSettingsError::TooManyUpdates { count, max } => write!(
+                f,
+                "Block has {} update proposals, more than the maximum of {}",
+                count, max
+            ),
+            BlockSettingsError::TooManyCertificates { count, max } => write!(
+                f,
+                "Block has {} certificates, more than the maximum of {}",
+                count, max
+            ),
         }
+    }
+}
+impl std::error::Error for BlockSettingsError {}
+
+/// error when a block is not acceptable for the chain, see `Block::validate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockValidationError {
+    /// the contents do not match the hash and size declared in the header
+    Inconsistent,
+    /// the block version is unknown or not used by the chain's consensus
+    UnsupportedVersion {
+        version: AnyBlockVersion,
+        consensus: ConsensusVersion,
+    },
+    /// the block does not fit the chain's settings
+    Settings(BlockSettingsError),
+    /// the block holds more transactions than allowed
+    TooManyTransactions { count: usize, max: u32 },
+    /// the BFT proof is not a valid signature from one of the leaders
+    InvalidBftProof,
+}
 
-        let hash = Hash::hash_bytes(&bytes);
-        (hash, bytes.len())
+impl std::fmt::Display for BlockValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BlockValidationError::Inconsistent => write!(
+                f,
+                "Block contents do not match the hash or size declared in the header"
+            ),
+            BlockValidationError::UnsupportedVersion { version, consensus } => write!(
+                f,
+                "Block version {} is not supported by the {} consensus",
+                Into::<u16>::into(*version),
+                consensus
+            ),
+            BlockValidationError::Settings(e) => e.fmt(f),
+            BlockValidationError::TooManyTransactions { count, max } => write!(
+                f,
+                "Block has {} transactions, more than the maximum of {}",
+                count, max
+            ),
+            BlockValidationError::InvalidBftProof => {
+                write!(f, "Block BFT proof is not signed by one of the leaders")
+            }
+        }
+    }
+}
+impl std::error::Error for BlockValidationError {}
+
+impl From<BlockSettingsError> for BlockValidationError {
+    fn from(e: BlockSettingsError) -> Self {
+        BlockValidationError::Settings(e)
+    }
+}
+
+/// error when a sequence of blocks is not a chain, see `validate_chain`.
+/// `index` is the position in the sequence of the block that does not
+/// follow the previous one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainLinkError {
+    /// the parent of the block is not the previous block
+    ParentMismatch {
+        index: usize,
+        expected: BlockId,
+        found: BlockId,
+    },
+    /// the chain length of the block is not the previous block's plus one
+    ChainLengthMismatch {
+        index: usize,
+        previous: ChainLength,
+        found: ChainLength,
+    },
+}
+
+impl std::fmt::Display for ChainLinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ChainLinkError::ParentMismatch {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Block {} has parent {} but the previous block is {}",
+                index, found, expected
+            ),
+            ChainLinkError::ChainLengthMismatch {
+                index,
+                previous,
+                found,
+            } => write!(
+                f,
+                "Block {} has chain length {} but the previous block has {}",
+                index, found, previous
+            ),
+        }
     }
 }
+impl std::error::Error for ChainLinkError {}
+
+/// check that each block of the sequence is the child of the previous
+/// one: its parent is the previous block and its chain length is one
+/// more. Fails on the first block that does not follow. The blocks
+/// themselves are not validated.
+pub fn validate_chain<'a, I: IntoIterator<Item = &'a Block>>(
+    blocks: I,
+) -> Result<(), ChainLinkError> {
+    let mut blocks = blocks.into_iter();
+    let mut previous = match blocks.next() {
+        None => return Ok(()),
+        Some(block) => block,
+    };
+    for (index, block) in (1..).zip(blocks) {
+        let expected = previous.header.hash();
+        let found = *block.header.block_parent_hash();
+        if found != expected {
+            return Err(ChainLinkError::ParentMismatch {
+                index,
+                expected,
+                found,
+            });
+        }
+        let found = block.header.chain_length();
+        if found.checked_sub(previous.header.chain_length()) != Some(1) {
+            return Err(ChainLinkError::ChainLengthMismatch {
+                index,
+                previous: previous.header.chain_length(),
+                found,
+            });
+        }
+        previous = block;
+    }
+    Ok(())
+}
+
+/// Number of messages of each kind in a block
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MessageCounts {
+    pub initial: usize,
+    pub old_utxo_declaration: usize,
+    pub transaction: usize,
+    pub certificate: usize,
+    pub update: usize,
+}
+
+/// The position of a block in the chain, computed once from its header,
+/// so chain walkers can carry it around instead of the whole block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockReference {
+    pub id: BlockId,
+    pub parent_id: BlockId,
+    pub date: BlockDate,
+    pub chain_length: ChainLength,
+}
 
 impl Block {
+    /// The id, parent id, date and chain length of the block
+    pub fn reference(&self) -> BlockReference {
+        BlockReference {
+            id: self.header.hash(),
+            parent_id: *self.header.block_parent_hash(),
+            date: *self.header.block_date(),
+            chain_length: self.header.chain_length(),
+        }
+    }
+
+    /// tell whether the two blocks are an equivocation: the same leader
+    /// produced two different blocks for the same date. The leader is the
+    /// BFT leader for BFT blocks and the stake pool for GenesisPraos
+    /// blocks. Blocks without proof, such as the genesis block, have no
+    /// leader and are never an equivocation.
+    ///
+    /// The proofs themselves are not verified.
+    pub fn is_equivocation_with(&self, other: &Block) -> bool {
+        let same_leader = match (self.header.proof(), other.header.proof()) {
+            (Proof::Bft(proof), Proof::Bft(other_proof)) => {
+                proof.leader_id == other_proof.leader_id
+            }
+            (Proof::GenesisPraos(proof), Proof::GenesisPraos(other_proof)) => {
+                proof.node_id == other_proof.node_id
+            }
+            _ => false,
+        };
+        same_leader
+            && self.header.block_date() == other.header.block_date()
+            && self.header.hash() != other.header.hash()
+    }
+
+    /// Count the messages of the block by kind, in a single pass
+    pub fn message_counts(&self) -> MessageCounts {
+        let mut counts = MessageCounts::default();
+        for message in self.contents.iter() {
+            match message {
+                Message::Initial(_) => counts.initial += 1,
+                Message::OldUtxoDeclaration(_) => counts.old_utxo_declaration += 1,
+                Message::Transaction(_) => counts.transaction += 1,
+                Message::Certificate(_) => counts.certificate += 1,
+                Message::Update(_) => counts.update += 1,
+            }
+        }
+        counts
+    }
+
+    /// The update proposals of the block, in the order they appear in
+    /// the block
+    pub fn update_proposals<'a>(&'a self) -> impl Iterator<Item = &'a UpdateProposal> + 'a {
+        self.contents.iter().filter_map(|message| match message {
+            Message::Update(proposal) => Some(proposal),
+            _ => None,
+        })
+    }
+
+    /// The stake pools referenced by the certificates of the block, in the
+    /// order they appear in the block. Pool registrations contribute the id
+    /// of the registered pool, delegations and retirements the id of the
+    /// pool they target; other messages and certificates do not contribute.
+    /// A pool referenced by several certificates is yielded once for each.
+    pub fn referenced_stake_pools<'a>(&'a self) -> impl Iterator<Item = StakePoolId> + 'a {
+        self.contents.iter().filter_map(|message| match message {
+            Message::Certificate(signed) => match &signed.transaction.extra.content {
+                CertificateContent::StakePoolRegistration(info) => Some(info.to_id()),
+                CertificateContent::StakeDelegation(delegation) => Some(delegation.pool_id.clone()),
+                CertificateContent::StakePoolRetirement(retirement) => {
+                    Some(retirement.pool_id.clone())
+                }
+                CertificateContent::StakeKeyRegistration(_)
+                | CertificateContent::StakeKeyDeregistration(_) => None,
+            },
+            _ => None,
+        })
+    }
+
+    /// The sum of the fees owed by the transactions and the certificates
+    /// of the block, as computed by the given fee algorithm. Other
+    /// messages do not contribute.
+    pub fn total_fees(&self, fee: &LinearFee) -> Result<Value, BalanceError> {
+        self.contents
+            .iter()
+            .try_fold(Value::zero(), |total, message| {
+                let message_fee = match message {
+                    Message::Transaction(signed) => fee.calculate_for(&signed.transaction),
+                    Message::Certificate(signed) => fee.calculate_for(&signed.transaction),
+                    _ => return Ok(total),
+                };
+                message_fee
+                    .and_then(|fee| total.checked_add(fee))
+                    .ok_or(BalanceError::ValueOverflow)
+            })
+    }
+
+    /// check the block against the limits set by the chain's settings
+    pub fn validate_settings(&self, settings: &Settings) -> Result<(), BlockSettingsError> {
+        let size = self.header.common.block_content_size;
+        if size > settings.max_block_content_size {
+            return Err(BlockSettingsError::ContentSizeTooBig {
+                size,
+                max: settings.max_block_content_size,
+            });
+        }
+
+        let counts = self.message_counts();
+        if counts.update > settings.max_updates_per_block as usize {
+            return Err(BlockSettingsError::TooManyUpdates {
+                count: counts.update,
+                max: settings.max_updates_per_block,
+            });
+        }
+        if counts.certificate > settings.max_certificates_per_block as usize {
+            return Err(BlockSettingsError::TooManyCertificates {
+                count: counts.certificate,
+                max: settings.max_certificates_per_block,
+            });
+        }
+        Ok(())
+    }
+
+    /// check the block is acceptable for a chain with the given settings
+    /// and BFT leaders. In order, the checks are:
+    ///
+    /// * the contents match the hash and size declared in the header;
+    /// * the block version is one of the versions of the consensus in use;
+    /// * the block fits the limits of `validate_settings`;
+    /// * the number of transactions is within the limit;
+    /// * the BFT proof, if the consensus is BFT, is signed by one of the
+    ///   leaders.
+    ///
+    /// The first failing check is returned. Genesis Praos proofs depend on
+    /// the stake distribution and are not verified here.
+    pub fn validate(
+        &self,
+        settings: &Settings,
+        leaders: &[bft::LeaderId],
+    ) -> Result<(), BlockValidationError> {
+        if !self.is_consistent() {
+            return Err(BlockValidationError::Inconsistent);
+        }
+
+        let version = self.header.block_version();
+        let consensus = settings.consensus_version;
+        let supported = consensus.supported_block_versions();
+        if !supported.iter().any(|supported| version == *supported) {
+            return Err(BlockValidationError::UnsupportedVersion { version, consensus });
+        }
+
+        self.validate_settings(settings)?;
+
+        let count = self.message_counts().transaction;
+        let max = settings.max_number_of_transactions_per_block;
+        if count > max as usize {
+            return Err(BlockValidationError::TooManyTransactions { count, max });
+        }
+
+        if consensus == ConsensusVersion::Bft
+            && self.header.verify_bft_proof(leaders) == Verification::Failed
+        {
+            return Err(BlockValidationError::InvalidBftProof);
+        }
+        Ok(())
+    }
+
     pub fn is_consistent(&self) -> bool {
         let (content_hash, content_size) = self.contents.compute_hash_size();
 
         &content_hash == self.header.block_content_hash()
             && content_size == self.header.common.block_content_size as usize
     }
+
+    /// Number of bytes written by `property::Serialize`, computed without
+    /// serializing anything.
+    ///
+    /// The size of the contents is not recomputed: the one declared in the
+    /// header is used, so the result is only exact for a consistent block
+    /// (see `is_consistent`).
+    pub fn serialized_size(&self) -> usize {
+        2 + self.header.serialized_size() + self.header.common.block_content_size as usize
+    }
+
+    /// Serialize the block in a vector allocated upfront with
+    /// `serialized_size`, so it is not grown along the way. For an
+    /// inconsistent block, whose declared content size is wrong, the
+    /// vector may still have to grow.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.serialized_size());
+        self.serialize(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Same as `property::Deserialize::deserialize`, but also recompute the
+    /// hash and size of the contents and error out if they do not match the
+    /// ones declared in the header.
+    pub fn deserialize_verified<R: std::io::BufRead>(reader: R) -> Result<Self, std::io::Error> {
+        let block = <Block as property::Deserialize>::deserialize(reader)?;
+        let (content_hash, content_size) = block.contents.compute_hash_size();
+
+        if content_size != block.header.common.block_content_size as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "block content size mismatch: header declares {} bytes, contents are {} bytes",
+                    block.header.common.block_content_size, content_size
+                ),
+            ));
+        }
+        if &content_hash != block.header.block_content_hash() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "block content hash mismatch: header declares {}, contents hash to {}",
+                    block.header.block_content_hash(),
+                    content_hash
+                ),
+            ));
+        }
+        Ok(block)
+    }
 }
 
 impl property::Block for Block {
@@ -136,20 +571,30 @@ impl property::Deserialize for Block {
         let header_raw = HeaderRaw::deserialize(&mut reader)?;
         let header = read_from_raw::<Header>(header_raw.as_ref())?;
 
-        let mut serialized_content_size = header.common.block_content_size;
+        let declared_content_size = header.common.block_content_size as usize;
+        let mut content_size = 0;
         let mut contents = BlockContents(Vec::with_capacity(4));
 
-        while serialized_content_size > 0 {
+        while content_size < declared_content_size {
             let message_raw = MessageRaw::deserialize(&mut reader)?;
-            let message_size = message_raw.size_bytes_plus_size();
-
-            // return error here if message serialize sized is bigger than remaining size
+            content_size += message_raw.size_bytes_plus_size();
+            if content_size > declared_content_size {
+                break;
+            }
 
             let message = Message::from_raw(&message_raw)
                 .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
             contents.0.push(message);
+        }
 
-            serialized_content_size -= message_size as u32;
+        if content_size != declared_content_size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "block content size mismatch: header declares {} bytes, messages take {} bytes",
+                    declared_content_size, content_size
+                ),
+            ));
         }
 
         Ok(Block {
@@ -159,6 +604,80 @@ impl property::Deserialize for Block {
     }
 }
 
+/// Read a batch of blocks prefixed by their number, as a big endian `u32`.
+///
+/// Fails without reading any block if the batch holds more than
+/// `max_blocks` blocks.
+pub fn read_block_batch<R: std::io::BufRead>(
+    mut reader: R,
+    max_blocks: usize,
+) -> Result<Vec<Block>, std::io::Error> {
+    use chain_core::packer::Codec;
+    use chain_core::property::Deserialize as _;
+
+    let count = Codec::from(&mut reader).get_u32()? as usize;
+    if count > max_blocks {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "block batch too big: {} blocks, at most {} accepted",
+                count, max_blocks
+            ),
+        ));
+    }
+    let mut blocks = Vec::with_capacity(count);
+    for _ in 0..count {
+        blocks.push(Block::deserialize(&mut reader)?);
+    }
+    Ok(blocks)
+}
+
+impl Block {
+    fn read_unlocated<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+        let header_size = buf.get_u16()? as usize;
+        let header_position = buf.position();
+        let mut header_buf = ReadBuf::from(buf.get_slice(header_size)?);
+        let header = Header::read(&mut header_buf)
+            .and_then(|header| header_buf.expect_end().map(|()| header))
+            .map_err(|e| header_buf.locate_error(e).shift_position(header_position))?;
+
+        let mut remaining_content_size = header.common.block_content_size as usize;
+        let mut contents = BlockContents(Vec::with_capacity(4));
+
+        while remaining_content_size > 0 {
+            let message_size = buf.get_u16()? as usize;
+            if message_size + 2 > remaining_content_size {
+                return Err(ReadError::StructureInvalid(format!(
+                    "message of {} bytes overflows the remaining {} bytes of block content",
+                    message_size + 2,
+                    remaining_content_size
+                )));
+            }
+            let message_position = buf.position();
+            let mut message_buf = ReadBuf::from(buf.get_slice(message_size)?);
+            let message = Message::read(&mut message_buf)
+                .and_then(|message| message_buf.expect_end().map(|()| message))
+                .map_err(|e| message_buf.locate_error(e).shift_position(message_position))?;
+            contents.0.push(message);
+
+            remaining_content_size -= message_size + 2;
+        }
+
+        Ok(Block { header, contents })
+    }
+}
+
+/// Read a block from a slice in a single pass, without going through
+/// `HeaderRaw` and `MessageRaw`.
+///
+/// Errors are located at the offset of the read that failed, including
+/// errors in the header or in the messages.
+impl Readable for Block {
+    fn read<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+        Self::read_unlocated(buf).map_err(|e| buf.locate_error(e))
+    }
+}
+
 impl property::HasMessages for Block {
     type Message = Message;
     fn messages<'a>(&'a self) -> Box<Iterator<Item = &Message> + 'a> {
@@ -184,7 +703,7 @@ impl property::HasHeader for Block {
 mod test {
 
     use super::*;
-    use quickcheck::{Arbitrary, Gen, TestResult};
+    use quickcheck::{Arbitrary, Gen, StdThreadGen, TestResult};
 
     quickcheck! {
         fn headerraw_serialization_bijection(b: HeaderRaw) -> TestResult {
@@ -198,6 +717,687 @@ mod test {
         fn block_serialization_bijection(b: Block) -> TestResult {
             property::testing::serialization_bijection(b)
         }
+
+        fn block_serialization_bijection_r(b: Block) -> TestResult {
+            property::testing::serialization_bijection_r(b)
+        }
+
+        fn message_ids_stable_across_serialization(b: Block) -> bool {
+            use chain_core::property::Deserialize;
+            let bytes = b.serialize_as_vec().unwrap();
+            let decoded = Block::deserialize(bytes.as_slice()).unwrap();
+            b.contents.message_ids().eq(decoded.contents.message_ids())
+                && b.contents.message_ids().count() == b.contents.iter().count()
+        }
+
+        fn block_builder_bft_block_is_consistent(
+            parent: BlockId,
+            date: BlockDate,
+            chain_length: u32,
+            contents: BlockContents,
+            leader: chain_crypto::KeyPair<chain_crypto::Ed25519Extended>
+        ) -> bool {
+            let chain_length = ChainLength(chain_length.saturating_add(1));
+            let mut builder = BlockBuilder::new();
+            builder
+                .parent(parent)
+                .date(date)
+                .chain_length(chain_length)
+                .messages(contents.iter().cloned());
+            let block = builder.make_bft_block(leader.private_key());
+
+            block.is_consistent()
+                && block.header.block_parent_hash() == &parent
+                && block.header.block_date() == &date
+                && block.header.chain_length() == chain_length
+                && block.contents == contents
+        }
+
+        fn block_deserialize_verified_accepts_consistent(b: Block) -> bool {
+            let bytes = b.serialize_as_vec().unwrap();
+            let decoded = Block::deserialize_verified(bytes.as_slice()).unwrap();
+            decoded == b && decoded.contents == b.contents
+        }
+
+        fn block_deserialize_verified_rejects_wrong_hash(b: Block, hash: Hash) -> TestResult {
+            use chain_core::property::Deserialize;
+            if &hash == b.header.block_content_hash() {
+                return TestResult::discard();
+            }
+            let mut b = b;
+            b.header.common.block_content_hash = hash;
+            let bytes = b.serialize_as_vec().unwrap();
+            let permissive = Block::deserialize(bytes.as_slice());
+            let verified = Block::deserialize_verified(bytes.as_slice());
+            TestResult::from_bool(
+                permissive.is_ok()
+                    && verified.map_err(|e| e.kind()).err()
+                        == Some(std::io::ErrorKind::InvalidData)
+            )
+        }
+
+        fn block_contents_collect_matches_new(messages: Vec<Message>) -> bool {
+            let (first, second) = messages.split_at(messages.len() / 2);
+            let mut extended: BlockContents = first.iter().cloned().collect();
+            extended.extend(second.iter().cloned());
+
+            let expected = BlockContents::new(messages.clone());
+            messages.iter().cloned().collect::<BlockContents>() == expected
+                && extended == expected
+        }
+
+        fn block_deserialize_rejects_content_size_overrun(b: Block, extra: Message) -> bool {
+            use chain_core::property::Deserialize;
+            let mut b = b;
+            b.header.common.block_content_size += 1;
+            let mut bytes = b.serialize_as_vec().unwrap();
+            let truncated = Block::deserialize(bytes.as_slice());
+
+            extra.to_raw().serialize(&mut bytes).unwrap();
+            let overrun = Block::deserialize(bytes.as_slice());
+
+            truncated.is_err()
+                && overrun.map_err(|e| e.kind()).err() == Some(std::io::ErrorKind::InvalidData)
+        }
+
+        fn block_builder_incremental_content_hash(contents: BlockContents) -> bool {
+            let mut builder = BlockBuilder::new();
+            for message in contents.iter() {
+                builder.message(message.clone());
+            }
+            let block = builder.make_genesis_block();
+            let (hash, size) = contents.compute_hash_size();
+
+            block.header.block_content_hash() == &hash
+                && block.header.common.block_content_size as usize == size
+                && BlockBuilder::from(block.clone()).make_genesis_block().header == block.header
+        }
+
+        fn message_serialized_size_sums_to_content_size(contents: BlockContents) -> bool {
+            let total: usize = contents.iter().map(Message::serialized_size).sum();
+            total == contents.compute_hash_size().1
+        }
+
+        fn compute_messages_hash_size_custom_messages(contents: BlockContents) -> bool {
+            struct Prerendered(MessageRaw);
+            impl ToMessageRaw for Prerendered {
+                fn to_raw(&self) -> MessageRaw {
+                    self.0.clone()
+                }
+            }
+
+            let custom: Vec<Prerendered> = contents
+                .iter()
+                .map(|message| Prerendered(message.to_raw()))
+                .collect();
+            compute_messages_hash_size(&custom) == contents.compute_hash_size()
+        }
+
+        fn block_to_bytes(b: Block) -> bool {
+            use chain_core::property::Deserialize;
+            let bytes = b.to_bytes();
+            bytes.len() == b.serialized_size()
+                && bytes == b.serialize_as_vec().unwrap()
+                && Block::deserialize(bytes.as_slice()).unwrap() == b
+        }
+
+        fn block_reference_matches_accessors(b: Block) -> bool {
+            use chain_core::property::Block as _;
+            let reference = b.reference();
+            reference.id == b.id()
+                && reference.parent_id == b.parent_id()
+                && reference.date == b.date()
+                && reference.chain_length == b.chain_length()
+        }
+
+        fn block_read_matches_deserialize(b: Block) -> bool {
+            use chain_core::property::Deserialize;
+            let bytes = b.serialize_as_vec().unwrap();
+            let read = Block::read(&mut ReadBuf::from(&bytes)).unwrap();
+            let deserialized = Block::deserialize(bytes.as_slice()).unwrap();
+            read == deserialized && read.contents == deserialized.contents
+        }
+    }
+
+    fn block_batch_bytes(blocks: &[Block]) -> Vec<u8> {
+        let mut bytes = (blocks.len() as u32).to_be_bytes().to_vec();
+        for block in blocks {
+            block.serialize(&mut bytes).unwrap();
+        }
+        bytes
+    }
+
+    #[test]
+    fn read_block_batch_roundtrip() {
+        let mut g = StdThreadGen::new(10);
+        let blocks: Vec<Block> = (0..3).map(|_| Block::arbitrary(&mut g)).collect();
+        let bytes = block_batch_bytes(&blocks);
+
+        let read = read_block_batch(bytes.as_slice(), 3).unwrap();
+        assert_eq!(read, blocks);
+        for (read, block) in read.iter().zip(blocks.iter()) {
+            assert_eq!(read.contents, block.contents);
+        }
+
+        let err = read_block_batch(bytes.as_slice(), 2).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn block_message_counts() {
+        let mut g = StdThreadGen::new(10);
+        let mut messages = Vec::new();
+        for _ in 0..3 {
+            messages.push(Message::Transaction(Arbitrary::arbitrary(&mut g)));
+        }
+        for _ in 0..2 {
+            messages.push(Message::Update(Arbitrary::arbitrary(&mut g)));
+        }
+        messages.push(Message::Certificate(Arbitrary::arbitrary(&mut g)));
+        messages.push(Message::Transaction(Arbitrary::arbitrary(&mut g)));
+
+        let mut builder = BlockBuilder::new();
+        builder.messages(messages);
+        let block = builder.make_genesis_block();
+
+        assert_eq!(
+            block.message_counts(),
+            MessageCounts {
+                initial: 0,
+                old_utxo_declaration: 0,
+                transaction: 4,
+                certificate: 1,
+                update: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn block_read_error_position() {
+        let mut builder = BlockBuilder::new();
+        builder
+            .message(Message::Update(UpdateProposal::new()))
+            .message(Message::Update(UpdateProposal::new()));
+        let block = builder.make_genesis_block();
+        let mut bytes = block.serialize_as_vec().unwrap();
+
+        // header size, header, then the size of the first message
+        let header_size = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+        let first_message_size =
+            u16::from_be_bytes([bytes[2 + header_size], bytes[3 + header_size]]);
+        // skip the first message and the size of the second one
+        let kind_position = 2 + header_size + 2 + first_message_size as usize + 2;
+        bytes[kind_position] = 0xff;
+
+        assert_eq!(
+            Block::read(&mut ReadBuf::from(&bytes)).unwrap_err(),
+            ReadError::AtPosition(kind_position, Box::new(ReadError::UnknownTag(0xff)))
+        );
+    }
+
+    #[test]
+    fn block_update_proposals() {
+        let mut g = StdThreadGen::new(10);
+        let updates: Vec<UpdateProposal> = std::iter::repeat_with(|| Arbitrary::arbitrary(&mut g))
+            .take(3)
+            .collect();
+
+        let mut builder = BlockBuilder::new();
+        builder
+            .message(Message::Transaction(Arbitrary::arbitrary(&mut g)))
+            .message(Message::Update(updates[0].clone()))
+            .message(Message::Update(updates[1].clone()))
+            .message(Message::Certificate(Arbitrary::arbitrary(&mut g)))
+            .message(Message::Transaction(Arbitrary::arbitrary(&mut g)))
+            .message(Message::Update(updates[2].clone()));
+        let block = builder.make_genesis_block();
+
+        assert!(block.update_proposals().eq(updates.iter()));
+        assert_eq!(
+            BlockBuilder::new()
+                .make_genesis_block()
+                .update_proposals()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn block_total_fees() {
+        use crate::certificate::Certificate;
+        use crate::transaction::AuthenticatedTransaction;
+        use chain_addr::Address;
+
+        let mut g = StdThreadGen::new(10);
+        let transactions: Vec<AuthenticatedTransaction<Address, _>> =
+            (0..2).map(|_| Arbitrary::arbitrary(&mut g)).collect();
+        let certificate: AuthenticatedTransaction<Address, Certificate> =
+            Arbitrary::arbitrary(&mut g);
+        let mut builder = BlockBuilder::new();
+        builder
+            .message(Message::Transaction(transactions[0].clone()))
+            .message(Message::Update(UpdateProposal::new()))
+            .message(Message::Certificate(certificate.clone()))
+            .message(Message::Transaction(transactions[1].clone()));
+        let block = builder.make_genesis_block();
+
+        let sizes = transactions
+            .iter()
+            .map(|signed| {
+                let tx = &signed.transaction;
+                (tx.inputs.len() + tx.outputs.len()) as u64
+            })
+            .collect::<Vec<_>>();
+        let certificate_size =
+            (certificate.transaction.inputs.len() + certificate.transaction.outputs.len()) as u64;
+        let expected: u64 =
+            sizes.iter().map(|size| 10 + 2 * size).sum::<u64>() + 10 + 2 * certificate_size + 100;
+        assert_eq!(
+            block.total_fees(&LinearFee::new(10, 2, 100)),
+            Ok(Value(expected))
+        );
+        assert_eq!(
+            block.total_fees(&LinearFee::new(u64::MAX, 0, 0)),
+            Err(BalanceError::ValueOverflow)
+        );
+        assert_eq!(
+            BlockBuilder::new()
+                .make_genesis_block()
+                .total_fees(&LinearFee::new(10, 2, 100)),
+            Ok(Value::zero())
+        );
+    }
+
+    #[test]
+    fn block_referenced_stake_pools() {
+        use crate::certificate::{Certificate, StakeDelegation, StakeKeyRegistration};
+        use crate::stake::StakePoolInfo;
+        use crate::transaction::AuthenticatedTransaction;
+        use chain_addr::Address;
+
+        let mut g = StdThreadGen::new(10);
+        let pool = StakePoolInfo::arbitrary(&mut g);
+        let delegation = StakeDelegation::arbitrary(&mut g);
+        let registration = StakeKeyRegistration::arbitrary(&mut g);
+        let mut certificate = |content| {
+            let mut signed: AuthenticatedTransaction<Address, Certificate> =
+                Arbitrary::arbitrary(&mut g);
+            signed.transaction.extra.content = content;
+            Message::Certificate(signed)
+        };
+
+        let mut builder = BlockBuilder::new();
+        builder
+            .message(Message::Update(UpdateProposal::new()))
+            .message(certificate(CertificateContent::StakePoolRegistration(
+                pool.clone(),
+            )))
+            .message(certificate(CertificateContent::StakeKeyRegistration(
+                registration,
+            )))
+            .message(certificate(CertificateContent::StakeDelegation(
+                delegation.clone(),
+            )));
+        let block = builder.make_genesis_block();
+
+        assert_eq!(
+            block.referenced_stake_pools().collect::<Vec<_>>(),
+            vec![pool.to_id(), delegation.pool_id]
+        );
+        assert_eq!(
+            BlockBuilder::new()
+                .make_genesis_block()
+                .referenced_stake_pools()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn block_validate_settings_content_size() {
+        let mut g = StdThreadGen::new(10);
+        let mut builder = BlockBuilder::new();
+        builder.message(Message::Transaction(Arbitrary::arbitrary(&mut g)));
+        let block = builder.make_genesis_block();
+        let size = block.header.common.block_content_size;
+
+        let mut settings = Settings::new();
+        settings.max_block_content_size = size;
+        assert_eq!(block.validate_settings(&settings), Ok(()));
+
+        settings.max_block_content_size = size - 1;
+        assert_eq!(
+            block.validate_settings(&settings),
+            Err(BlockSettingsError::ContentSizeTooBig {
+                size,
+                max: size - 1
+            })
+        );
+    }
+
+    #[test]
+    fn block_validate_settings_message_kinds() {
+        let mut g = StdThreadGen::new(10);
+        let mut builder = BlockBuilder::new();
+        builder
+            .message(Message::Update(UpdateProposal::new()))
+            .message(Message::Certificate(Arbitrary::arbitrary(&mut g)))
+            .message(Message::Update(UpdateProposal::new()));
+        let block = builder.make_genesis_block();
+
+        let mut settings = Settings::new();
+        assert_eq!(block.validate_settings(&settings), Ok(()));
+
+        settings.max_updates_per_block = 2;
+        settings.max_certificates_per_block = 1;
+        assert_eq!(block.validate_settings(&settings), Ok(()));
+
+        settings.max_updates_per_block = 1;
+        assert_eq!(
+            block.validate_settings(&settings),
+            Err(BlockSettingsError::TooManyUpdates { count: 2, max: 1 })
+        );
+
+        settings.max_updates_per_block = 2;
+        settings.max_certificates_per_block = 0;
+        assert_eq!(
+            block.validate_settings(&settings),
+            Err(BlockSettingsError::TooManyCertificates { count: 1, max: 0 })
+        );
+    }
+
+    fn validation_bft_block(g: &mut StdThreadGen, transactions: usize) -> (Block, bft::LeaderId) {
+        let leader = chain_crypto::KeyPair::<chain_crypto::Ed25519Extended>::arbitrary(g);
+        let mut builder = BlockBuilder::new();
+        builder
+            .parent(Arbitrary::arbitrary(g))
+            .date(Arbitrary::arbitrary(g))
+            .chain_length(ChainLength(1));
+        for _ in 0..transactions {
+            builder.message(Message::Transaction(Arbitrary::arbitrary(g)));
+        }
+        let block = builder.make_bft_block(leader.private_key());
+        (block, bft::LeaderId::from(leader.public_key().clone()))
+    }
+
+    #[test]
+    fn block_is_equivocation_with() {
+        use chain_crypto::{testing::keypair_from_seed, Ed25519Extended, KeyPair};
+
+        let mut g = StdThreadGen::new(10);
+        let leader: KeyPair<Ed25519Extended> = keypair_from_seed(0);
+        let other_leader: KeyPair<Ed25519Extended> = keypair_from_seed(1);
+        let date = BlockDate::arbitrary(&mut g);
+        let update = |max_block_content_size| {
+            let mut update = UpdateProposal::new();
+            update.max_block_content_size = Some(max_block_content_size);
+            Message::Update(update)
+        };
+        let builder = |date, max_block_content_size| {
+            let mut builder = BlockBuilder::new();
+            builder
+                .date(date)
+                .chain_length(ChainLength(1))
+                .message(update(max_block_content_size));
+            builder
+        };
+        let genesis = |max_block_content_size| {
+            let mut builder = BlockBuilder::new();
+            builder.message(update(max_block_content_size));
+            builder.make_genesis_block()
+        };
+
+        let block = builder(date, 1).make_bft_block(leader.private_key());
+        let same_slot = builder(date, 2).make_bft_block(leader.private_key());
+        let later_slot = builder(date.next_epoch(), 2).make_bft_block(leader.private_key());
+        let other_leader = builder(date, 2).make_bft_block(other_leader.private_key());
+
+        assert!(!block.is_equivocation_with(&block.clone()));
+        assert!(block.is_equivocation_with(&same_slot));
+        assert!(same_slot.is_equivocation_with(&block));
+        assert!(!block.is_equivocation_with(&later_slot));
+        assert!(!block.is_equivocation_with(&other_leader));
+        assert!(!genesis(1).is_equivocation_with(&genesis(2)));
+    }
+
+    #[test]
+    fn validate_chain_links() {
+        use chain_crypto::{testing::keypair_from_seed, Ed25519Extended, KeyPair};
+
+        let leader: KeyPair<Ed25519Extended> = keypair_from_seed(0);
+        let child = |parent: &Block, chain_length| {
+            let mut builder = BlockBuilder::new();
+            builder
+                .parent(parent.header.hash())
+                .date(parent.header.block_date().next_epoch())
+                .chain_length(ChainLength(chain_length));
+            builder.make_bft_block(leader.private_key())
+        };
+        let genesis = BlockBuilder::new().make_genesis_block();
+        let first = child(&genesis, 1);
+        let second = child(&first, 2);
+        let third = child(&second, 3);
+
+        assert_eq!(validate_chain(&[]), Ok(()));
+        assert_eq!(validate_chain(std::iter::once(&second)), Ok(()));
+        assert_eq!(
+            validate_chain(&[
+                genesis.clone(),
+                first.clone(),
+                second.clone(),
+                third.clone()
+            ]),
+            Ok(())
+        );
+
+        assert_eq!(
+            validate_chain(&[genesis.clone(), first.clone(), third.clone()]),
+            Err(ChainLinkError::ParentMismatch {
+                index: 2,
+                expected: first.header.hash(),
+                found: second.header.hash(),
+            })
+        );
+        let skipped = child(&first, 3);
+        assert_eq!(
+            validate_chain(vec![&genesis, &first, &skipped]),
+            Err(ChainLinkError::ChainLengthMismatch {
+                index: 2,
+                previous: ChainLength(1),
+                found: ChainLength(3),
+            })
+        );
+    }
+
+    #[test]
+    fn block_validate_accepts_valid_block() {
+        let mut g = StdThreadGen::new(10);
+        let (block, leader) = validation_bft_block(&mut g, 2);
+        assert_eq!(block.validate(&Settings::new(), &[leader]), Ok(()));
+    }
+
+    #[test]
+    fn block_validate_inconsistent() {
+        let mut g = StdThreadGen::new(10);
+        let (mut block, leader) = validation_bft_block(&mut g, 2);
+        block.header.common.block_content_size += 1;
+        assert_eq!(
+            block.validate(&Settings::new(), &[leader]),
+            Err(BlockValidationError::Inconsistent)
+        );
+    }
+
+    #[test]
+    fn block_validate_unsupported_version() {
+        let block = BlockBuilder::new().make_genesis_block();
+        assert_eq!(
+            block.validate(&Settings::new(), &[]),
+            Err(BlockValidationError::UnsupportedVersion {
+                version: AnyBlockVersion::Supported(BlockVersion::Genesis),
+                consensus: ConsensusVersion::Bft,
+            })
+        );
+    }
+
+    #[test]
+    fn block_validate_settings() {
+        let mut g = StdThreadGen::new(10);
+        let (block, leader) = validation_bft_block(&mut g, 2);
+        let size = block.header.common.block_content_size;
+        let mut settings = Settings::new();
+        settings.max_block_content_size = size - 1;
+        assert_eq!(
+            block.validate(&settings, &[leader]),
+            Err(BlockValidationError::Settings(
+                BlockSettingsError::ContentSizeTooBig {
+                    size,
+                    max: size - 1
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn block_validate_too_many_transactions() {
+        let mut g = StdThreadGen::new(10);
+        let (block, leader) = validation_bft_block(&mut g, 2);
+        let mut settings = Settings::new();
+        settings.max_number_of_transactions_per_block = 2;
+        assert_eq!(
+            block.validate(&settings, std::slice::from_ref(&leader)),
+            Ok(())
+        );
+
+        settings.max_number_of_transactions_per_block = 1;
+        assert_eq!(
+            block.validate(&settings, &[leader]),
+            Err(BlockValidationError::TooManyTransactions { count: 2, max: 1 })
+        );
+    }
+
+    #[test]
+    fn block_validate_invalid_bft_proof() {
+        let mut g = StdThreadGen::new(10);
+        let (block, leader) = validation_bft_block(&mut g, 2);
+        let other_leader = std::iter::repeat_with(|| bft::LeaderId::arbitrary(&mut g))
+            .find(|other| *other != leader)
+            .unwrap();
+        assert_eq!(
+            block.validate(&Settings::new(), &[other_leader]),
+            Err(BlockValidationError::InvalidBftProof)
+        );
+        assert_eq!(
+            block.validate(&Settings::new(), &[]),
+            Err(BlockValidationError::InvalidBftProof)
+        );
+    }
+
+    fn consensus_builder(g: &mut StdThreadGen) -> BlockBuilder {
+        let mut builder = BlockBuilder::new();
+        builder
+            .parent(Arbitrary::arbitrary(g))
+            .date(Arbitrary::arbitrary(g))
+            .chain_length(ChainLength(1))
+            .messages(BlockContents::arbitrary(g).iter().cloned());
+        builder
+    }
+
+    #[test]
+    fn block_builder_bft_block_for_consensus() {
+        use crate::key::verify_signature;
+        use chain_crypto::{Ed25519Extended, KeyPair, Verification};
+
+        let mut g = StdThreadGen::new(10);
+        let leader = KeyPair::<Ed25519Extended>::arbitrary(&mut g);
+
+        let block = consensus_builder(&mut g)
+            .make_bft_block_for(ConsensusVersion::Bft, leader.private_key())
+            .unwrap();
+        assert!(block.is_consistent());
+        assert_eq!(block.header.proof_kind(), Some(ConsensusVersion::Bft));
+        match block.header.proof() {
+            Proof::Bft(proof) => {
+                assert_eq!(proof.leader_id.as_public_key(), leader.public_key());
+                assert_eq!(
+                    verify_signature(
+                        &proof.signature.0,
+                        leader.public_key(),
+                        &block.header.common
+                    ),
+                    Verification::Success
+                );
+            }
+            proof => panic!("unexpected proof {:?}", proof),
+        }
+
+        assert_eq!(
+            consensus_builder(&mut g)
+                .make_bft_block_for(ConsensusVersion::GenesisPraos, leader.private_key())
+                .unwrap_err(),
+            BlockBuilderError::ConsensusMismatch {
+                expected: ConsensusVersion::GenesisPraos,
+                proof: ConsensusVersion::Bft,
+            }
+        );
+    }
+
+    #[test]
+    fn block_builder_genesis_praos_block_for_consensus() {
+        use crate::key::verify_signature;
+        use chain_crypto::{
+            AsymmetricKey, Curve25519_2HashDH, FakeMMM, SecretKey, VerifiableRandomFunction,
+            Verification,
+        };
+        use rand_chacha::ChaChaRng;
+        use rand_core::SeedableRng;
+
+        let mut g = StdThreadGen::new(10);
+        let mut rng = ChaChaRng::from_seed([42; 32]);
+        let node_id = Arbitrary::arbitrary(&mut g);
+        let mut kes_key = SecretKey::<FakeMMM>::arbitrary(&mut g);
+        let kes_public_key = kes_key.to_public();
+        let vrf_key = Curve25519_2HashDH::generate(&mut rng);
+        let mut vrf_proof =
+            || Curve25519_2HashDH::evaluate_and_proove(&vrf_key, &[0, 1, 2, 3], &mut rng);
+
+        let block = consensus_builder(&mut g)
+            .make_genesis_praos_block_for(
+                ConsensusVersion::GenesisPraos,
+                &node_id,
+                &mut kes_key,
+                vrf_proof(),
+            )
+            .unwrap();
+        assert!(block.is_consistent());
+        assert_eq!(
+            block.header.proof_kind(),
+            Some(ConsensusVersion::GenesisPraos)
+        );
+        match block.header.proof() {
+            Proof::GenesisPraos(proof) => {
+                assert_eq!(proof.node_id, node_id);
+                assert_eq!(
+                    verify_signature(&proof.kes_proof.0, &kes_public_key, &block.header.common),
+                    Verification::Success
+                );
+            }
+            proof => panic!("unexpected proof {:?}", proof),
+        }
+
+        assert_eq!(
+            consensus_builder(&mut g)
+                .make_genesis_praos_block_for(
+                    ConsensusVersion::Bft,
+                    &node_id,
+                    &mut kes_key,
+                    vrf_proof(),
+                )
+                .unwrap_err(),
+            BlockBuilderError::ConsensusMismatch {
+                expected: ConsensusVersion::Bft,
+                proof: ConsensusVersion::GenesisPraos,
+            }
+        );
     }
 
     impl Arbitrary for HeaderRaw {
@@ -18,6 +18,13 @@ impl AnyBlockVersion {
             AnyBlockVersion::Unsupported(_) => None,
         }
     }
+
+    pub fn is_supported(&self) -> bool {
+        match self {
+            AnyBlockVersion::Supported(_) => true,
+            AnyBlockVersion::Unsupported(_) => false,
+        }
+    }
 }
 
 impl PartialEq<BlockVersion> for AnyBlockVersion {
@@ -75,6 +82,7 @@ impl BlockVersion {
     Clone,
     Copy,
     Display,
+    EnumIter,
     EnumString,
     FromPrimitive,
     IntoStaticStr,
@@ -87,7 +95,7 @@ impl BlockVersion {
 pub enum ConsensusVersion {
     #[strum(to_string = "bft")]
     Bft = 1,
-    #[strum(to_string = "genesis")]
+    #[strum(to_string = "genesis-praos", serialize = "genesis")]
     GenesisPraos = 2,
 }
 
@@ -110,3 +118,44 @@ impl ConsensusVersion {
             .unwrap_or_default()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn any_block_version_known() {
+        let version = AnyBlockVersion::from(BlockVersion::Ed25519Signed as u16);
+        assert!(version.is_supported());
+        assert_eq!(
+            version.try_into_block_version(),
+            Some(BlockVersion::Ed25519Signed)
+        );
+    }
+
+    #[test]
+    fn any_block_version_unknown() {
+        let version = AnyBlockVersion::from(42);
+        assert!(!version.is_supported());
+        assert_eq!(version.try_into_block_version(), None);
+        assert_eq!(Into::<u16>::into(version), 42);
+    }
+
+    #[test]
+    fn consensus_version_display_from_str_roundtrip() {
+        for version in ConsensusVersion::iter() {
+            assert_eq!(version.to_string().parse(), Ok(version));
+        }
+        assert_eq!(ConsensusVersion::Bft.to_string(), "bft");
+        assert_eq!(ConsensusVersion::GenesisPraos.to_string(), "genesis-praos");
+        assert_eq!("genesis".parse(), Ok(ConsensusVersion::GenesisPraos));
+    }
+
+    #[test]
+    fn consensus_version_from_str_unknown() {
+        assert_eq!(
+            "praos".parse::<ConsensusVersion>(),
+            Err(strum::ParseError::VariantNotFound)
+        );
+    }
+}
@@ -314,7 +314,7 @@ impl StakePoolInfo {
 
 impl HasStakeKeyIds for StakePoolInfo {
     fn public_keys<'a>(&'a self) -> Box<ExactSizeIterator<Item = &StakeKeyId> + 'a> {
-        Box::new(self.owners.iter())
+        Box::new(self.owners())
     }
 }
 
@@ -336,7 +336,7 @@ impl StakePoolRetirement {
 
 impl HasStakeKeyIds for StakePoolRetirement {
     fn public_keys<'a>(&'a self) -> Box<ExactSizeIterator<Item = &StakeKeyId> + 'a> {
-        Box::new(self.pool_info.owners.iter())
+        Box::new(self.pool_info.owners())
     }
 }
 
@@ -363,8 +363,6 @@ impl Readable for StakePoolRetirement {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::leadership::genesis::GenesisPraosLeader;
-    use chain_crypto::SecretKey;
     use quickcheck::{Arbitrary, Gen};
 
     impl Arbitrary for Certificate {
@@ -415,25 +413,6 @@ mod test {
         }
     }
 
-    impl Arbitrary for StakePoolInfo {
-        fn arbitrary<G: Gen>(g: &mut G) -> Self {
-            use rand_core::SeedableRng;
-            let mut seed = [0; 32];
-            for byte in seed.iter_mut() {
-                *byte = Arbitrary::arbitrary(g);
-            }
-            let mut rng = rand_chacha::ChaChaRng::from_seed(seed);
-            StakePoolInfo {
-                serial: Arbitrary::arbitrary(g),
-                owners: vec![Arbitrary::arbitrary(g)],
-                initial_key: GenesisPraosLeader {
-                    vrf_public_key: SecretKey::generate(&mut rng).to_public(),
-                    kes_public_key: SecretKey::generate(&mut rng).to_public(),
-                },
-            }
-        }
-    }
-
     impl Arbitrary for StakePoolRetirement {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
             StakePoolRetirement {
@@ -65,16 +65,27 @@ impl Readable for ConfigParam {
     }
 }
 
+impl ConfigParam {
+    /// the tag identifying the kind of parameter in the binary encoding
+    pub(crate) fn tag(&self) -> Tag {
+        match self {
+            ConfigParam::Block0Date(_) => Block0Date::TAG,
+            ConfigParam::Discrimination(_) => Discrimination::TAG,
+            ConfigParam::ConsensusVersion(_) => ConsensusVersion::TAG,
+        }
+    }
+}
+
 impl property::Serialize for ConfigParam {
     type Error = io::Error;
 
     fn serialize<W: Write>(&self, writer: W) -> Result<(), Self::Error> {
-        let (tag, bytes) = match self {
-            ConfigParam::Block0Date(data) => (Block0Date::TAG, data.to_payload()),
-            ConfigParam::Discrimination(data) => (Discrimination::TAG, data.to_payload()),
-            ConfigParam::ConsensusVersion(data) => (ConsensusVersion::TAG, data.to_payload()),
+        let bytes = match self {
+            ConfigParam::Block0Date(data) => data.to_payload(),
+            ConfigParam::Discrimination(data) => data.to_payload(),
+            ConfigParam::ConsensusVersion(data) => data.to_payload(),
         };
-        let taglen = TagLen::new(tag, bytes.len()).ok_or_else(|| {
+        let taglen = TagLen::new(self.tag(), bytes.len()).ok_or_else(|| {
             io::Error::new(
                 io::ErrorKind::InvalidData,
                 "initial ent payload too big".to_string(),
@@ -120,6 +131,35 @@ mod serde_impl {
             .serialize(serializer)
         }
     }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        quickcheck! {
+            fn config_param_json_roundtrip(param: ConfigParam) -> bool {
+                let json = serde_json::to_string(&param).unwrap();
+                serde_json::from_str::<ConfigParam>(&json).unwrap() == param
+            }
+        }
+
+        #[test]
+        fn config_param_json_is_tagged_by_name() {
+            assert_eq!(
+                serde_json::to_value(ConfigParam::Block0Date(Block0Date(42))).unwrap(),
+                serde_json::json!(["block0-date", "42"])
+            );
+            assert_eq!(
+                serde_json::to_value(ConfigParam::Discrimination(Discrimination::Test)).unwrap(),
+                serde_json::json!(["discrimination", "test"])
+            );
+            assert_eq!(
+                serde_json::to_value(ConfigParam::ConsensusVersion(ConsensusVersion::Bft)).unwrap(),
+                serde_json::json!(["block0-consensus", "bft"])
+            );
+            assert!(serde_json::from_str::<ConfigParam>(r#"["unknown", "42"]"#).is_err());
+        }
+    }
 }
 
 trait ConfigParamVariant: Clone + Eq + PartialEq {
@@ -233,7 +273,7 @@ impl ConfigParamVariant for ConsensusVersion {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-struct Tag(u16);
+pub(crate) struct Tag(u16);
 
 impl Tag {
     pub const fn new(tag: u16) -> Self {
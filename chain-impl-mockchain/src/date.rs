@@ -5,6 +5,9 @@ use std::{error, fmt, num::ParseIntError, str};
 /// Non unique identifier of the transaction position in the
 /// blockchain. There may be many transactions related to the same
 /// `SlotId`.
+///
+/// Dates are ordered by epoch, then by slot within the epoch: the derived
+/// ordering relies on the declaration order of the fields.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct BlockDate {
     pub epoch: Epoch,
@@ -26,12 +29,16 @@ impl BlockDate {
 
     /// Get the slot following this one.
     pub fn next(&self) -> BlockDate {
-        assert!(self.slot_id < EPOCH_DURATION);
-        if self.slot_id + 1 == EPOCH_DURATION {
-            BlockDate {
-                epoch: self.epoch + 1,
-                slot_id: 0,
-            }
+        self.next_slot(EPOCH_DURATION)
+    }
+
+    /// Get the slot following this one, rolling over to the first slot
+    /// of the next epoch after the last slot of an epoch made of
+    /// `slots_per_epoch` slots.
+    pub fn next_slot(&self, slots_per_epoch: u32) -> BlockDate {
+        assert!(self.slot_id < slots_per_epoch);
+        if self.slot_id + 1 == slots_per_epoch {
+            self.next_epoch()
         } else {
             BlockDate {
                 epoch: self.epoch,
@@ -40,6 +47,7 @@ impl BlockDate {
         }
     }
 
+    /// Get the first slot of the following epoch.
     pub fn next_epoch(&self) -> BlockDate {
         BlockDate {
             epoch: self.epoch + 1,
@@ -109,12 +117,113 @@ impl str::FromStr for BlockDate {
     }
 }
 
+#[cfg(feature = "generic-serialization")]
+mod serde_impl {
+    use super::*;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    /// a `BlockDate` is serialized in the same `EPOCH.SLOT` form
+    /// as the one of `Display` and `FromStr`
+    impl Serialize for BlockDate {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&self.to_string())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for BlockDate {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct BlockDateVisitor;
+
+            impl<'de> de::Visitor<'de> for BlockDateVisitor {
+                type Value = BlockDate;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    write!(f, "a block date in the format EPOCH.SLOT")
+                }
+
+                fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                    v.parse().map_err(E::custom)
+                }
+            }
+
+            deserializer.deserialize_str(BlockDateVisitor)
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        quickcheck! {
+            fn block_date_json_roundtrip(date: BlockDate) -> bool {
+                let json = serde_json::to_string(&date).unwrap();
+                json == format!("\"{}\"", date)
+                    && serde_json::from_str::<BlockDate>(&json).unwrap() == date
+            }
+        }
+
+        #[test]
+        fn block_date_json_invalid() {
+            assert!(serde_json::from_str::<BlockDate>("\"42\"").is_err());
+            assert!(serde_json::from_str::<BlockDate>("{\"epoch\":1,\"slot_id\":2}").is_err());
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use quickcheck::{Arbitrary, Gen};
     use std::error::Error;
 
+    #[test]
+    fn next_slot_mid_epoch() {
+        let date = BlockDate {
+            epoch: 3,
+            slot_id: 5,
+        };
+        assert_eq!(
+            date.next_slot(10),
+            BlockDate {
+                epoch: 3,
+                slot_id: 6
+            }
+        );
+    }
+
+    #[test]
+    fn next_slot_last_slot_of_epoch() {
+        let date = BlockDate {
+            epoch: 3,
+            slot_id: 9,
+        };
+        assert_eq!(
+            date.next_slot(10),
+            BlockDate {
+                epoch: 4,
+                slot_id: 0
+            }
+        );
+        assert_eq!(date.next_slot(10), date.next_epoch());
+    }
+
+    #[test]
+    fn block_date_ordering() {
+        let date = |epoch, slot_id| BlockDate { epoch, slot_id };
+        assert!(date(1, 5) < date(2, 0));
+        assert!(date(1, 4) < date(1, 5));
+        assert_eq!(date(1, 5).cmp(&date(1, 5)), std::cmp::Ordering::Equal);
+
+        let mut dates = std::collections::BTreeMap::new();
+        dates.insert(date(2, 0), "c");
+        dates.insert(date(1, 5), "b");
+        dates.insert(date(1, 4), "a");
+        assert_eq!(
+            dates.values().cloned().collect::<Vec<_>>(),
+            vec!["a", "b", "c"]
+        );
+    }
+
     #[test]
     fn parse_no_dot() {
         let err = "42".parse::<BlockDate>().unwrap_err();
@@ -153,6 +262,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_epoch_overflow() {
+        let err = "4294967296.12".parse::<BlockDate>().unwrap_err();
+        if let BlockDateParseError::BadEpochId(_) = err {
+            println!("{}: {}", err, err.source().unwrap());
+        } else {
+            panic!("unexpected error {:?}", err);
+        }
+    }
+
+    quickcheck! {
+        fn display_parse_roundtrip(date: BlockDate) -> bool {
+            date.to_string().parse() == Ok(date)
+        }
+    }
+
     impl Arbitrary for BlockDate {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
             BlockDate {
@@ -1,45 +1,141 @@
+use crate::certificate::Certificate;
 use crate::transaction as tx;
 use crate::value::Value;
 use chain_addr::Address;
+use chain_core::mempack::{ReadBuf, ReadError, Readable};
+use chain_core::property;
 
 /// Linear fee using the basic affine formula
 /// `COEFFICIENT * bytes(COUNT(tx.inputs) + COUNT(tx.outputs)) + CONSTANT + CERTIFICATE*COUNT(certificates)`.
 #[derive(PartialEq, Eq, PartialOrd, Debug, Clone, Copy)]
 pub struct LinearFee {
-    pub constant: u64,
-    pub coefficient: u64,
-    pub certificate: u64,
+    pub constant: Value,
+    pub coefficient: Value,
+    pub certificate: Value,
 }
 
 impl LinearFee {
     pub fn new(constant: u64, coefficient: u64, certificate: u64) -> Self {
         LinearFee {
-            constant,
-            coefficient,
-            certificate,
+            constant: Value(constant),
+            coefficient: Value(coefficient),
+            certificate: Value(certificate),
         }
     }
 }
 
+/// The constant, the coefficient and the certificate fee, in that order,
+/// as in the linear fee update of `UpdateProposal`.
+impl property::Serialize for LinearFee {
+    type Error = std::io::Error;
+    fn serialize<W: std::io::Write>(&self, mut writer: W) -> Result<(), Self::Error> {
+        self.constant.serialize(&mut writer)?;
+        self.coefficient.serialize(&mut writer)?;
+        self.certificate.serialize(&mut writer)?;
+        Ok(())
+    }
+}
+
+impl Readable for LinearFee {
+    fn read<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+        Ok(LinearFee {
+            constant: Value::read(buf)?,
+            coefficient: Value::read(buf)?,
+            certificate: Value::read(buf)?,
+        })
+    }
+}
+
+/// The number of certificates carried by the extra of a transaction,
+/// each of them being charged `LinearFee::certificate`.
+pub trait CertificateCount {
+    fn certificate_count(&self) -> u64;
+}
+
+impl CertificateCount for tx::NoExtra {
+    fn certificate_count(&self) -> u64 {
+        0
+    }
+}
+
+impl CertificateCount for Certificate {
+    fn certificate_count(&self) -> u64 {
+        1
+    }
+}
+
 pub trait FeeAlgorithm {
-    fn calculate_for<Extra>(&self, tx: &tx::Transaction<Address, Extra>) -> Option<Value>;
+    fn calculate_for<Extra: CertificateCount>(
+        &self,
+        tx: &tx::Transaction<Address, Extra>,
+    ) -> Option<Value>;
 }
 
 impl<'a, FA: FeeAlgorithm> FeeAlgorithm for &'a FA {
-    fn calculate_for<Extra>(&self, tx: &tx::Transaction<Address, Extra>) -> Option<Value> {
+    fn calculate_for<Extra: CertificateCount>(
+        &self,
+        tx: &tx::Transaction<Address, Extra>,
+    ) -> Option<Value> {
         (*self).calculate_for(tx)
     }
 }
 
 impl FeeAlgorithm for LinearFee {
-    fn calculate_for<Extra>(&self, tx: &tx::Transaction<Address, Extra>) -> Option<Value> {
+    fn calculate_for<Extra: CertificateCount>(
+        &self,
+        tx: &tx::Transaction<Address, Extra>,
+    ) -> Option<Value> {
         let msz = (tx.inputs.len() as u64).checked_add(tx.outputs.len() as u64)?;
-        // FIXME for now we don't consider extra as payload, however in the near future
-        // we need a trait related to the Extra that will give the fee valuation of the certificate
-        let fee = self
-            .coefficient
+        let certificates = self.certificate.checked_mul(tx.extra.certificate_count())?;
+        self.coefficient
             .checked_mul(msz)?
-            .checked_add(self.constant)?;
-        Some(Value(fee))
+            .checked_add(self.constant)?
+            .checked_add(certificates)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use quickcheck::{Arbitrary, Gen, TestResult};
+
+    impl Arbitrary for LinearFee {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            LinearFee::new(
+                Arbitrary::arbitrary(g),
+                Arbitrary::arbitrary(g),
+                Arbitrary::arbitrary(g),
+            )
+        }
+    }
+
+    quickcheck! {
+        fn linear_fee_serialization_bijection(fee: LinearFee) -> TestResult {
+            property::testing::serialization_bijection_r(fee)
+        }
+    }
+
+    #[test]
+    fn linear_fee_charges_certificates() {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let signed: tx::AuthenticatedTransaction<Address, Certificate> =
+            Arbitrary::arbitrary(&mut g);
+        let with_certificate = signed.transaction;
+        let without_certificate = with_certificate.clone().replace_extra(tx::NoExtra);
+        let msz = (with_certificate.inputs.len() + with_certificate.outputs.len()) as u64;
+
+        let fee = LinearFee::new(10, 2, 100);
+        assert_eq!(
+            fee.calculate_for(&with_certificate),
+            Some(Value(10 + 2 * msz + 100))
+        );
+        assert_eq!(
+            fee.calculate_for(&without_certificate),
+            Some(Value(10 + 2 * msz))
+        );
+
+        let fee = LinearFee::new(1, 0, u64::MAX);
+        assert_eq!(fee.calculate_for(&with_certificate), None);
+        assert_eq!(fee.calculate_for(&without_certificate), Some(Value(1)));
     }
 }
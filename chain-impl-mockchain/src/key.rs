@@ -60,20 +60,83 @@ pub fn deserialize_public_key<'a, A>(
 where
     A: AsymmetricKey,
 {
-    let mut bytes = vec![0u8; A::PUBLIC_KEY_SIZE];
-    read_mut_slice(buf, &mut bytes[..])?;
-    crypto::PublicKey::from_binary(&bytes).map_err(chain_crypto_pub_err)
+    deserialize_public_key_with_label(buf, "public key")
 }
 #[inline]
 pub fn deserialize_signature<'a, A, T>(
     buf: &mut ReadBuf<'a>,
 ) -> Result<crypto::Signature<T, A>, ReadError>
+where
+    A: VerificationAlgorithm,
+{
+    deserialize_signature_with_label(buf, "signature")
+}
+
+fn read_error_with_label(label: &'static str, e: ReadError) -> ReadError {
+    ReadError::Context(label, Box::new(e))
+}
+
+/// same as `deserialize_public_key`, errors are wrapped in a
+/// `ReadError::Context` with the given label to tell which field was
+/// being read
+pub fn deserialize_public_key_with_label<'a, A>(
+    buf: &mut ReadBuf<'a>,
+    label: &'static str,
+) -> Result<crypto::PublicKey<A>, ReadError>
+where
+    A: AsymmetricKey,
+{
+    let mut bytes = vec![0u8; A::PUBLIC_KEY_SIZE];
+    read_mut_slice(buf, &mut bytes[..]).map_err(|e| read_error_with_label(label, e))?;
+    crypto::PublicKey::from_binary(&bytes)
+        .map_err(chain_crypto_pub_err)
+        .map_err(|e| read_error_with_label(label, e))
+}
+
+/// same as `deserialize_signature`, errors are wrapped in a
+/// `ReadError::Context` with the given label to tell which field was
+/// being read
+pub fn deserialize_signature_with_label<'a, A, T>(
+    buf: &mut ReadBuf<'a>,
+    label: &'static str,
+) -> Result<crypto::Signature<T, A>, ReadError>
 where
     A: VerificationAlgorithm,
 {
     let mut bytes = vec![0u8; A::SIGNATURE_SIZE];
-    read_mut_slice(buf, &mut bytes[..])?;
-    crypto::Signature::from_binary(&bytes).map_err(chain_crypto_sig_err)
+    read_mut_slice(buf, &mut bytes[..]).map_err(|e| read_error_with_label(label, e))?;
+    crypto::Signature::from_binary(&bytes)
+        .map_err(chain_crypto_sig_err)
+        .map_err(|e| read_error_with_label(label, e))
+}
+
+/// Serialize the public key preceded by its size in bytes, as a big
+/// endian `u16`. To use where the key algorithm, hence the key size, is
+/// not known by the reader, `serialize_public_key` otherwise.
+pub fn serialize_public_key_prefixed<A: AsymmetricKey, W: std::io::Write>(
+    key: &crypto::PublicKey<A>,
+    mut writer: W,
+) -> Result<(), std::io::Error> {
+    let bytes = key.as_ref();
+    if bytes.len() > u16::MAX as usize {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "public key too large to be length prefixed".to_string(),
+        ));
+    }
+    writer.write_all(&(bytes.len() as u16).to_be_bytes())?;
+    writer.write_all(bytes)
+}
+/// Deserialize a public key serialized with `serialize_public_key_prefixed`
+pub fn deserialize_public_key_prefixed<'a, A>(
+    buf: &mut ReadBuf<'a>,
+) -> Result<crypto::PublicKey<A>, ReadError>
+where
+    A: AsymmetricKey,
+{
+    let size = buf.get_u16()? as usize;
+    let bytes = buf.get_slice(size)?;
+    crypto::PublicKey::from_binary(bytes).map_err(chain_crypto_pub_err)
 }
 
 pub fn make_signature<T, A>(
@@ -200,6 +263,46 @@ impl Hash {
     pub fn from_bytes(bytes: [u8; 32]) -> Self {
         Hash(crypto::Blake2b256::from(bytes))
     }
+    pub fn hasher() -> Hasher {
+        Hasher(crypto::Blake2b256::hasher())
+    }
+    /// Hash the serialized value, without allocating for the serialization.
+    pub fn hash_of<T: property::Serialize>(value: &T) -> Result<Self, T::Error> {
+        let mut hasher = Hash::hasher();
+        value.serialize(&mut hasher)?;
+        Ok(hasher.finalize())
+    }
+    /// Compare two hashes in a time that does not depend on their content.
+    pub fn constant_time_eq(&self, other: &Self) -> bool {
+        self.0.constant_time_eq(&other.0)
+    }
+}
+
+/// Incremental computation of a `Hash`, for inputs made of several parts
+/// that would otherwise need to be concatenated for `Hash::hash_bytes`.
+pub struct Hasher(crypto::Blake2bHasher<crypto::Blake2b256>);
+impl Hasher {
+    pub fn update(&mut self, bytes: &[u8]) -> &mut Self {
+        self.0.update(bytes);
+        self
+    }
+    /// Get the hash of the bytes given so far, leaving the hasher
+    /// reset as if freshly created by `Hash::hasher`.
+    pub fn finalize(&mut self) -> Hash {
+        let hasher = std::mem::replace(&mut self.0, crypto::Blake2b256::hasher());
+        Hash(hasher.finalize())
+    }
+}
+
+impl std::io::Write for Hasher {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.update(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
 }
 
 impl property::Serialize for Hash {
@@ -252,6 +355,15 @@ impl std::fmt::Display for Hash {
     }
 }
 
+impl std::fmt::LowerHex for Hash {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+        write!(f, "{}", self.0)
+    }
+}
+
 impl FromStr for Hash {
     type Err = crypto::hash::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -264,6 +376,154 @@ pub mod test {
     use super::*;
     use quickcheck::{Arbitrary, Gen};
 
+    quickcheck! {
+        fn hash_display_from_str_roundtrip(hash: Hash) -> bool {
+            hash.to_string().parse() == Ok(hash)
+                && format!("{:x}", hash) == hash.to_string()
+        }
+    }
+
+    quickcheck! {
+        fn hasher_chunked_matches_hash_bytes(data: Vec<u8>, chunk_size: usize) -> bool {
+            let mut hasher = Hash::hasher();
+            for chunk in data.chunks(chunk_size % 16 + 1) {
+                hasher.update(chunk);
+            }
+            hasher.finalize() == Hash::hash_bytes(&data)
+        }
+
+        fn hasher_chained_matches_hash_bytes(data: Vec<u8>) -> bool {
+            Hash::hasher().update(&data).finalize() == Hash::hash_bytes(&data)
+        }
+
+        fn hasher_reset_after_finalize(first: Vec<u8>, second: Vec<u8>) -> bool {
+            let mut hasher = Hash::hasher();
+            hasher.update(&first).finalize();
+            hasher.update(&second).finalize() == Hash::hash_bytes(&second)
+        }
+
+        fn hash_of_matches_hash_bytes(update: crate::setting::UpdateProposal) -> bool {
+            let bytes = property::Serialize::serialize_as_vec(&update).unwrap();
+            Hash::hash_of(&update).unwrap() == Hash::hash_bytes(&bytes)
+        }
+    }
+
+    fn public_key_prefixed_roundtrip<A: AsymmetricKey>(key: crypto::PublicKey<A>) -> bool {
+        let mut bytes = Vec::new();
+        serialize_public_key_prefixed(&key, &mut bytes).unwrap();
+        let mut buf = ReadBuf::from(&bytes);
+        let decoded = deserialize_public_key_prefixed::<A>(&mut buf).unwrap();
+        buf.expect_end().unwrap();
+        bytes[..2] == (A::PUBLIC_KEY_SIZE as u16).to_be_bytes() && decoded == key
+    }
+
+    quickcheck! {
+        fn public_key_prefixed_roundtrip_ed25519(key: crypto::PublicKey<crypto::Ed25519>) -> bool {
+            public_key_prefixed_roundtrip(key)
+        }
+
+        fn public_key_prefixed_roundtrip_ed25519_bip32(
+            key: crypto::PublicKey<crypto::Ed25519Bip32>
+        ) -> bool {
+            public_key_prefixed_roundtrip(key)
+        }
+    }
+
+    #[test]
+    fn secret_key_debug_is_redacted() {
+        use rand_chacha::ChaChaRng;
+        use rand_core::{RngCore, SeedableRng};
+
+        let mut bytes = [0u8; 64];
+        ChaChaRng::from_seed([7; 32]).fill_bytes(&mut bytes);
+        let secret_key = SpendingSecretKey::from_binary(&bytes).unwrap();
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+        let debug = format!("{:?}", secret_key);
+        assert!(debug.contains("<redacted>"));
+        assert!(!debug.contains(&hex[..16]));
+        assert!(!debug.contains(&hex[hex.len() - 16..]));
+    }
+
+    #[test]
+    fn deserialize_with_label_error() {
+        // not a canonical ristretto point
+        let bytes = [0xffu8; 32];
+        let mut buf = ReadBuf::from(&bytes);
+        let err = deserialize_public_key_with_label::<crypto::Curve25519_2HashDH>(
+            &mut buf,
+            "vrf public key",
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("while reading vrf public key: "));
+
+        let mut buf = ReadBuf::from(&bytes);
+        let err = deserialize_public_key::<crypto::Curve25519_2HashDH>(&mut buf)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("while reading public key: "));
+    }
+
+    #[test]
+    fn deserialize_with_label_keeps_not_enough_bytes() {
+        let bytes = [0u8; 10];
+        let mut buf = ReadBuf::from(&bytes);
+        let err = deserialize_signature_with_label::<crypto::Ed25519, ()>(
+            &mut buf,
+            "account witness signature",
+        )
+        .err()
+        .unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("while reading account witness signature: NotEnoughBytes"));
+        match err {
+            ReadError::Context("account witness signature", e) => match *e {
+                ReadError::NotEnoughBytes(_, _) => (),
+                e => panic!("unexpected error {:?}", e),
+            },
+            e => panic!("unexpected error {:?}", e),
+        }
+
+        let mut buf = ReadBuf::from(&bytes);
+        match deserialize_public_key::<crypto::Ed25519>(&mut buf) {
+            Err(ReadError::Context("public key", e)) => match *e {
+                ReadError::NotEnoughBytes(_, _) => (),
+                e => panic!("unexpected error {:?}", e),
+            },
+            r => panic!("unexpected result {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn public_key_prefixed_size_mismatch() {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let key = crypto::PublicKey::<crypto::Ed25519Bip32>::arbitrary(&mut g);
+        let mut bytes = Vec::new();
+        serialize_public_key_prefixed(&key, &mut bytes).unwrap();
+        let mut buf = ReadBuf::from(&bytes);
+        assert!(deserialize_public_key_prefixed::<crypto::Ed25519>(&mut buf).is_err());
+    }
+
+    #[test]
+    fn hash_constant_time_eq() {
+        let h1 = Hash::hash_bytes(&[1, 2, 3]);
+        let h2 = Hash::hash_bytes(&[1, 2, 4]);
+        assert!(h1.constant_time_eq(&h1));
+        assert_eq!(h1.constant_time_eq(&h2), h1 == h2);
+        assert!(!h1.constant_time_eq(&h2));
+    }
+
+    #[test]
+    fn hash_from_str_errors() {
+        assert_eq!(
+            "0011".parse::<Hash>(),
+            Err(crypto::hash::Error::InvalidHashSize(2, 32))
+        );
+        assert!("not hex".parse::<Hash>().is_err());
+    }
+
     impl Arbitrary for Hash {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
             Hash(Arbitrary::arbitrary(g))
@@ -56,7 +56,7 @@ impl BftLeaderSelection {
         match &block_header.proof() {
             Proof::Bft(bft_proof) => match self.get_leader_at(*block_header.block_date()) {
                 Ok(leader_at) => {
-                    if bft_proof.leader_id != leader_at {
+                    if !bft_proof.leader_id.0.constant_time_eq(&leader_at.0) {
                         Verification::Failure(Error::new(ErrorKind::InvalidLeader))
                     } else {
                         Verification::Success
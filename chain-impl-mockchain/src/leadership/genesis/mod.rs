@@ -1,4 +1,4 @@
-mod vrfeval;
+pub(crate) mod vrfeval;
 
 use crate::{
     block::{BlockDate, Header, Proof},
@@ -11,7 +11,7 @@ use crate::{
 };
 use chain_crypto::Verification as SigningVerification;
 use chain_crypto::{Curve25519_2HashDH, FakeMMM, PublicKey, SecretKey};
-pub use vrfeval::Witness;
+pub use vrfeval::{Nonce, Witness};
 
 /// Praos Leader consisting of the KES public key and VRF public key
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -7,6 +7,7 @@ use chain_crypto::{
     SecretKey, VRFVerification, VerifiableRandomFunction,
 };
 use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
 
 /// Nonce gathered per block
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -88,8 +89,7 @@ pub fn evaluate(
     slotid: SlotId,
 ) -> Option<Witness> {
     let input = Input::create(nonce, slotid);
-    let csprng = OsRng::new().unwrap();
-    let vr = vrf_evaluate_and_proove(key, &input.0, csprng);
+    let vr = evaluate_witness(key, nonce, slotid, OsRng::new().unwrap());
     let r = vrf_verified_get_output::<Curve25519_2HashDH>(&vr);
     let t = get_threshold(&input, &r);
     if above_stake_threshold(t, my_stake) {
@@ -99,6 +99,18 @@ pub fn evaluate(
     }
 }
 
+/// evaluate the VRF for the given nonce and slot, regardless of the stake
+/// threshold.
+pub fn evaluate_witness<T: RngCore + CryptoRng>(
+    key: &SecretKey<Curve25519_2HashDH>,
+    nonce: &Nonce,
+    slotid: SlotId,
+    rng: T,
+) -> Witness {
+    let input = Input::create(nonce, slotid);
+    vrf_evaluate_and_proove(key, &input.0, rng)
+}
+
 /// verify that the witness pass the threshold for this witness for a given
 /// key and its associated stake.
 ///
@@ -124,6 +136,18 @@ pub fn verify(
     }
 }
 
+/// verify the witness is a valid VRF proof for the given key, nonce and
+/// slot, without checking it against the stake threshold.
+pub fn verify_witness(
+    key: &PublicKey<Curve25519_2HashDH>,
+    nonce: &Nonce,
+    slotid: SlotId,
+    witness: &Witness,
+) -> VRFVerification {
+    let input = Input::create(nonce, slotid);
+    vrf_verify(key, &input.0, witness)
+}
+
 fn above_stake_threshold(threshold: Threshold, stake: PercentStake) -> bool {
     // TODO F is hardcoded here
     threshold >= phi(F::create(0.5), stake)
@@ -4,13 +4,13 @@
 use crate::block::{ChainLength, ConsensusVersion, HeaderHash};
 use crate::config::{self, ConfigParam};
 use crate::fee::LinearFee;
-use crate::message::Message;
+use crate::message::{InitialEntsError, Message};
 use crate::stake::{DelegationError, DelegationState, StakeDistribution};
 use crate::transaction::*;
 use crate::value::*;
 use crate::{account, certificate, legacy, setting, stake, utxo};
 use chain_addr::{Address, Discrimination, Kind};
-use chain_core::property::{self, ChainLength as _};
+use chain_core::property;
 use std::sync::Arc;
 
 // static parameters, effectively this is constant in the parameter of the blockchain
@@ -61,6 +61,7 @@ pub struct Ledger {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Error {
     Config(config::Error),
+    Setting(setting::Error),
     NotEnoughSignatures(usize, usize),
     UtxoValueNotMatching(Value, Value),
     UtxoError(utxo::Error),
@@ -75,6 +76,8 @@ pub enum Error {
     Block0TransactionHasWitnesses,
     Block0InitialMessageMissing,
     Block0InitialMessageNoConsensus,
+    Block0InitialMessageInvalid(InitialEntsError),
+    Block0SettingsInvalid(setting::Error),
     Block0UtxoTotalValueTooBig,
     UtxoInputsTotal(ValueError),
     UtxoOutputsTotal(ValueError),
@@ -87,6 +90,7 @@ pub enum Error {
     ExpectingUtxoWitness,
     ExpectingInitialMessage,
     CertificateInvalidSignature,
+    ChainLengthOverflow,
 }
 
 impl From<utxo::Error> for Error {
@@ -113,6 +117,12 @@ impl From<config::Error> for Error {
     }
 }
 
+impl From<setting::Error> for Error {
+    fn from(e: setting::Error) -> Self {
+        Error::Setting(e)
+    }
+}
+
 impl Ledger {
     fn empty(static_parameters: LedgerStaticParameters) -> Self {
         Ledger {
@@ -138,6 +148,8 @@ impl Ledger {
 
         let static_parameters = match content_iter.next() {
             Some(Message::Initial(ref ents)) => {
+                ents.validate_unique()
+                    .map_err(Error::Block0InitialMessageInvalid)?;
                 let mut params = LedgerStaticParameters::default();
                 let mut consensus = None;
                 for config in ents.iter() {
@@ -161,6 +173,7 @@ impl Ledger {
         }?;
 
         let mut ledger = Self::empty(static_parameters);
+        ledger.settings.consensus_version = ledger.static_params.block0_consensus;
 
         for content in content_iter {
             match content {
@@ -190,7 +203,9 @@ impl Ledger {
                     ledger.accounts = new_accounts;;
                 }
                 Message::Update(update_proposal) => {
-                    ledger = ledger.apply_update(&update_proposal)?;
+                    // the settings are only complete once all the updates of
+                    // block0 are applied, so they are validated after the loop
+                    ledger.settings = ledger.settings.apply(&update_proposal);
                     ledger_params = ledger.get_ledger_parameters();
                 }
                 Message::Certificate(authenticated_cert_tx) => {
@@ -210,6 +225,10 @@ impl Ledger {
             }
         }
 
+        ledger
+            .settings
+            .validate()
+            .map_err(Error::Block0SettingsInvalid)?;
         ledger.validate_utxo_total_value()?;
         Ok(ledger)
     }
@@ -225,7 +244,7 @@ impl Ledger {
     {
         let mut new_ledger = self.clone();
 
-        new_ledger.chain_length = self.chain_length.next();
+        new_ledger.chain_length = self.chain_length.next().ok_or(Error::ChainLengthOverflow)?;
 
         for content in contents {
             match content {
@@ -264,7 +283,7 @@ impl Ledger {
     }
 
     pub fn apply_update(mut self, update: &setting::UpdateProposal) -> Result<Self, Error> {
-        self.settings = self.settings.apply(update);
+        self.settings = self.settings.try_apply(update)?;
         Ok(self)
     }
 
@@ -596,6 +615,89 @@ pub mod test {
         };
     }
 
+    fn bft_leaders_update<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        leaders_nb: usize,
+    ) -> setting::UpdateProposal {
+        let mut update = setting::UpdateProposal::new();
+        update.bft_leaders = Some(
+            std::iter::repeat_with(|| SpendingSecretKey::generate(&mut *rng).to_public().into())
+                .take(leaders_nb)
+                .collect(),
+        );
+        update
+    }
+
+    fn block0_ents(consensus_version: ConsensusVersion) -> initial::InitialEnts {
+        let mut ie = initial::InitialEnts::new();
+        ie.push(ConfigParam::Discrimination(Discrimination::Test));
+        ie.push(ConfigParam::ConsensusVersion(consensus_version));
+        ie
+    }
+
+    #[test]
+    pub fn block0_settings_are_validated() {
+        let block0_hash = HeaderHash::hash_bytes(&[1, 2, 3]);
+        let mut rng = rand::thread_rng();
+
+        // BFT without any leader
+        let messages = [Message::Initial(block0_ents(ConsensusVersion::Bft))];
+        assert_err!(
+            Error::Block0SettingsInvalid(setting::Error::UpdateIsInvalid),
+            Ledger::new(block0_hash, &messages)
+        );
+
+        // the last update of block0 leaves no room for transactions
+        let mut update = setting::UpdateProposal::new();
+        update.max_number_of_transactions_per_block = Some(0);
+        let messages = [
+            Message::Initial(block0_ents(ConsensusVersion::Bft)),
+            Message::Update(bft_leaders_update(&mut rng, 2)),
+            Message::Update(update),
+        ];
+        assert_err!(
+            Error::Block0SettingsInvalid(setting::Error::UpdateIsInvalid),
+            Ledger::new(block0_hash, &messages)
+        );
+
+        // duplicated BFT leaders
+        let mut update = bft_leaders_update(&mut rng, 1);
+        let leaders = update.bft_leaders.as_mut().unwrap();
+        leaders.push(leaders[0].clone());
+        let messages = [
+            Message::Initial(block0_ents(ConsensusVersion::Bft)),
+            Message::Update(update),
+        ];
+        assert_err!(
+            Error::Block0SettingsInvalid(setting::Error::UpdateIsInvalid),
+            Ledger::new(block0_hash, &messages)
+        );
+
+        let messages = [
+            Message::Initial(block0_ents(ConsensusVersion::Bft)),
+            Message::Update(bft_leaders_update(&mut rng, 2)),
+        ];
+        assert!(Ledger::new(block0_hash, &messages).is_ok());
+    }
+
+    #[test]
+    pub fn genesis_praos_ledger_applies_updates_without_bft_leaders() {
+        let block0_hash = HeaderHash::hash_bytes(&[1, 2, 3]);
+        let messages = [Message::Initial(block0_ents(
+            ConsensusVersion::GenesisPraos,
+        ))];
+        let ledger = Ledger::new(block0_hash, &messages).unwrap();
+        assert_eq!(
+            ledger.settings.consensus_version,
+            ConsensusVersion::GenesisPraos
+        );
+
+        let mut update = setting::UpdateProposal::new();
+        update.slot_duration = Some(20);
+        let ledger = ledger.apply_update(&update).unwrap();
+        assert_eq!(ledger.settings.slot_duration, 20);
+    }
+
     #[test]
     pub fn utxo() -> () {
         let block0_hash = HeaderHash::hash_bytes(&[1, 2, 3]);
@@ -630,7 +732,11 @@ pub mod test {
             value: value,
         };
 
-        let messages = [Message::Initial(ie), Message::Transaction(first_trans)];
+        let messages = [
+            Message::Initial(ie),
+            Message::Update(bft_leaders_update(&mut rng, 1)),
+            Message::Transaction(first_trans),
+        ];
         let ledger = Ledger::new(block0_hash, &messages).unwrap();
         let dyn_params = ledger.get_ledger_parameters();
 
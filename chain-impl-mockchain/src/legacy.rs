@@ -7,7 +7,6 @@ use cardano::hdwallet::XPub;
 pub use cardano::address::Addr as OldAddress;
 use chain_core::mempack::{ReadBuf, ReadError, Readable};
 use chain_core::property;
-use chain_core::property::Serialize;
 use chain_crypto::{Ed25519Bip32, PublicKey};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -32,8 +31,7 @@ pub fn oldaddress_from_xpub(address: &OldAddress, xpub: &PublicKey<Ed25519Bip32>
 
 impl UtxoDeclaration {
     pub fn hash(&self) -> TransactionId {
-        let v = self.serialize_as_vec().unwrap();
-        TransactionId::hash_bytes(&v[..])
+        TransactionId::hash_of(self).unwrap()
     }
 }
 
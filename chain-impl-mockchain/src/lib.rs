@@ -1,3 +1,9 @@
+#![cfg_attr(feature = "with-bench", feature(test))]
+
+#[cfg(test)]
+#[cfg(feature = "with-bench")]
+extern crate test;
+
 #[cfg(test)]
 #[macro_use]
 extern crate quickcheck;
@@ -26,5 +32,12 @@ pub mod txbuilder;
 pub mod utxo;
 pub mod value;
 
+#[cfg(any(test, feature = "property-test-api"))]
+pub mod testing;
+
 #[cfg(test)]
 mod tests {}
+
+#[cfg(test)]
+#[cfg(feature = "with-bench")]
+mod bench;
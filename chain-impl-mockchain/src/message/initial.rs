@@ -1,6 +1,12 @@
-use crate::config::ConfigParam;
+use crate::block::ConsensusVersion;
+use crate::config::{Block0Date, ConfigParam};
+use chain_addr::Discrimination;
 use chain_core::mempack::{ReadBuf, ReadError, Readable};
 use chain_core::property;
+use std::fmt;
+
+/// maximum number of config parameters accepted in the initial message
+pub const MAX_INITIAL_ENTS: usize = 255;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(
@@ -10,6 +16,54 @@ use chain_core::property;
 )]
 pub struct InitialEnts(Vec<ConfigParam>);
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitialEntsError {
+    /// the same kind of config parameter is set more than once
+    DuplicateConfigParam(ConfigParam),
+    /// config parameters required by `Block0Config` are not set
+    MissingConfigParams(Vec<MissingParam>),
+}
+
+impl fmt::Display for InitialEntsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InitialEntsError::DuplicateConfigParam(param) => {
+                write!(f, "config parameter set more than once: {:?}", param)
+            }
+            InitialEntsError::MissingConfigParams(params) => {
+                write!(f, "missing config parameters: ")?;
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", param)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for InitialEntsError {}
+
+/// a kind of config parameter required in the initial message of block0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingParam {
+    Block0Date,
+    Discrimination,
+    ConsensusVersion,
+}
+
+impl fmt::Display for MissingParam {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MissingParam::Block0Date => write!(f, "block0 date"),
+            MissingParam::Discrimination => write!(f, "discrimination"),
+            MissingParam::ConsensusVersion => write!(f, "consensus version"),
+        }
+    }
+}
+
 impl InitialEnts {
     pub fn new() -> Self {
         InitialEnts(Vec::new())
@@ -22,6 +76,155 @@ impl InitialEnts {
     pub fn iter(&self) -> std::slice::Iter<ConfigParam> {
         self.0.iter()
     }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// the block0 date, if set. If it is set more than once, the first
+    /// occurrence is returned.
+    pub fn block0_date(&self) -> Option<Block0Date> {
+        self.iter()
+            .filter_map(|config| match config {
+                ConfigParam::Block0Date(date) => Some(*date),
+                _ => None,
+            })
+            .next()
+    }
+
+    /// the address discrimination, if set. If it is set more than once,
+    /// the first occurrence is returned.
+    pub fn discrimination(&self) -> Option<Discrimination> {
+        self.iter()
+            .filter_map(|config| match config {
+                ConfigParam::Discrimination(discrimination) => Some(*discrimination),
+                _ => None,
+            })
+            .next()
+    }
+
+    /// the block0 consensus version, if set. If it is set more than once,
+    /// the first occurrence is returned.
+    pub fn consensus_version(&self) -> Option<ConsensusVersion> {
+        self.iter()
+            .filter_map(|config| match config {
+                ConfigParam::ConsensusVersion(version) => Some(*version),
+                _ => None,
+            })
+            .next()
+    }
+
+    /// check that every config parameter required in block0 is set,
+    /// failing with the list of all the missing ones, in the order of
+    /// `MissingParam`.
+    pub fn require_all(&self) -> Result<(), Vec<MissingParam>> {
+        let mut missing = Vec::new();
+        if self.block0_date().is_none() {
+            missing.push(MissingParam::Block0Date);
+        }
+        if self.discrimination().is_none() {
+            missing.push(MissingParam::Discrimination);
+        }
+        if self.consensus_version().is_none() {
+            missing.push(MissingParam::ConsensusVersion);
+        }
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(missing)
+        }
+    }
+
+    /// Sort the config parameters by kind, so the serialized form (and so
+    /// the block0 hash) does not depend on the insertion order. Parameters
+    /// of the same kind keep their relative order.
+    pub fn canonicalize(&mut self) {
+        self.0.sort_by_key(ConfigParam::tag)
+    }
+
+    /// Same as `canonicalize`, taking ownership of the parameters
+    pub fn into_canonical(mut self) -> InitialEnts {
+        self.canonicalize();
+        self
+    }
+
+    /// check that no kind of config parameter appears more than once
+    pub fn validate_unique(&self) -> Result<(), InitialEntsError> {
+        for (i, config) in self.0.iter().enumerate() {
+            let kind = std::mem::discriminant(config);
+            if self.0[..i]
+                .iter()
+                .any(|previous| std::mem::discriminant(previous) == kind)
+            {
+                return Err(InitialEntsError::DuplicateConfigParam(config.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Merge `other` on top of `self`: every kind of config parameter set
+    /// in `other` replaces all the parameters of the same kind in `self`,
+    /// the kinds only set in `self` are kept.
+    ///
+    /// All the kinds of config parameters are single-valued for now, so
+    /// there is no list to append to: a kind set in `other` always wins.
+    pub fn merge(self, other: InitialEnts) -> InitialEnts {
+        let mut merged: Vec<ConfigParam> = self
+            .0
+            .into_iter()
+            .filter(|config| {
+                let kind = std::mem::discriminant(config);
+                !other
+                    .iter()
+                    .any(|overriding| std::mem::discriminant(overriding) == kind)
+            })
+            .collect();
+        merged.extend(other.0);
+        InitialEnts(merged)
+    }
+}
+
+impl Default for InitialEnts {
+    fn default() -> Self {
+        InitialEnts::new()
+    }
+}
+
+/// typed view of the config parameters of the initial message of block0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block0Config {
+    pub block0_date: Block0Date,
+    pub discrimination: Discrimination,
+    pub consensus_version: ConsensusVersion,
+}
+
+impl Block0Config {
+    /// the config parameters, in the canonical order of `InitialEnts::canonicalize`
+    pub fn to_initial_ents(&self) -> InitialEnts {
+        let mut ents = InitialEnts::new();
+        ents.push(ConfigParam::Block0Date(self.block0_date));
+        ents.push(ConfigParam::Discrimination(self.discrimination));
+        ents.push(ConfigParam::ConsensusVersion(self.consensus_version));
+        ents
+    }
+
+    /// fails if a config parameter is set more than once, or with all the
+    /// missing ones if some of the parameters are not set
+    pub fn from_initial_ents(ents: &InitialEnts) -> Result<Self, InitialEntsError> {
+        ents.validate_unique()?;
+        ents.require_all()
+            .map_err(InitialEntsError::MissingConfigParams)?;
+        // all the parameters are set, as checked by `require_all`
+        Ok(Block0Config {
+            block0_date: ents.block0_date().unwrap(),
+            discrimination: ents.discrimination().unwrap(),
+            consensus_version: ents.consensus_version().unwrap(),
+        })
+    }
 }
 
 impl property::Serialize for InitialEnts {
@@ -38,6 +241,12 @@ impl Readable for InitialEnts {
     fn read<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
         let mut configs = vec![];
         while !buf.is_end() {
+            if configs.len() == MAX_INITIAL_ENTS {
+                return Err(ReadError::SizeTooBig(
+                    MAX_INITIAL_ENTS + 1,
+                    MAX_INITIAL_ENTS,
+                ));
+            }
             configs.push(ConfigParam::read(buf)?);
         }
         Ok(InitialEnts(configs))
@@ -49,12 +258,260 @@ mod test {
     use super::*;
     use quickcheck::{Arbitrary, Gen, TestResult};
 
+    #[test]
+    fn validate_unique_rejects_duplicate_kind() {
+        let mut ents = InitialEnts::new();
+        ents.push(ConfigParam::Discrimination(Discrimination::Test));
+        ents.push(ConfigParam::ConsensusVersion(ConsensusVersion::Bft));
+        assert_eq!(ents.validate_unique(), Ok(()));
+
+        ents.push(ConfigParam::Discrimination(Discrimination::Production));
+        assert_eq!(
+            ents.validate_unique(),
+            Err(InitialEntsError::DuplicateConfigParam(
+                ConfigParam::Discrimination(Discrimination::Production)
+            ))
+        );
+    }
+
+    #[test]
+    fn len_tracks_pushes() {
+        let mut ents = InitialEnts::default();
+        assert!(ents.is_empty());
+        assert_eq!(ents.len(), 0);
+        assert_eq!(ents, InitialEnts::new());
+
+        ents.push(ConfigParam::Discrimination(Discrimination::Test));
+        ents.push(ConfigParam::Block0Date(Block0Date(42)));
+        assert!(!ents.is_empty());
+        assert_eq!(ents.len(), 2);
+        assert_eq!(ents.len(), ents.iter().count());
+    }
+
+    #[test]
+    fn typed_getters() {
+        let mut ents = InitialEnts::new();
+        assert_eq!(ents.block0_date(), None);
+        assert_eq!(ents.discrimination(), None);
+        assert_eq!(ents.consensus_version(), None);
+
+        ents.push(ConfigParam::Discrimination(Discrimination::Test));
+        ents.push(ConfigParam::Block0Date(Block0Date(42)));
+        ents.push(ConfigParam::ConsensusVersion(
+            ConsensusVersion::GenesisPraos,
+        ));
+        ents.push(ConfigParam::Block0Date(Block0Date(1234)));
+        assert_eq!(ents.block0_date(), Some(Block0Date(42)));
+        assert_eq!(ents.discrimination(), Some(Discrimination::Test));
+        assert_eq!(
+            ents.consensus_version(),
+            Some(ConsensusVersion::GenesisPraos)
+        );
+    }
+
+    #[test]
+    fn require_all_lists_missing_params() {
+        let mut ents = InitialEnts::new();
+        ents.push(ConfigParam::Discrimination(Discrimination::Test));
+        assert_eq!(
+            ents.require_all(),
+            Err(vec![
+                MissingParam::Block0Date,
+                MissingParam::ConsensusVersion
+            ])
+        );
+
+        ents.push(ConfigParam::ConsensusVersion(ConsensusVersion::Bft));
+        ents.push(ConfigParam::Block0Date(Block0Date(42)));
+        assert_eq!(ents.require_all(), Ok(()));
+    }
+
+    #[test]
+    fn merge_overrides_same_kind() {
+        let mut base = InitialEnts::new();
+        base.push(ConfigParam::Block0Date(Block0Date(42)));
+        base.push(ConfigParam::Discrimination(Discrimination::Production));
+        let mut overrides = InitialEnts::new();
+        overrides.push(ConfigParam::Discrimination(Discrimination::Test));
+        overrides.push(ConfigParam::ConsensusVersion(ConsensusVersion::Bft));
+
+        let mut merged = base.merge(overrides);
+        merged.canonicalize();
+        assert_eq!(merged.validate_unique(), Ok(()));
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged.block0_date(), Some(Block0Date(42)));
+        assert_eq!(merged.discrimination(), Some(Discrimination::Test));
+        assert_eq!(merged.consensus_version(), Some(ConsensusVersion::Bft));
+    }
+
+    #[test]
+    fn merge_with_empty() {
+        let mut ents = InitialEnts::new();
+        ents.push(ConfigParam::Block0Date(Block0Date(42)));
+        assert_eq!(ents.clone().merge(InitialEnts::new()), ents);
+        assert_eq!(InitialEnts::new().merge(ents.clone()), ents);
+    }
+
+    #[test]
+    fn block0_date_from_serialized_ents() {
+        use chain_core::property::Serialize;
+
+        let mut ents = InitialEnts::new();
+        ents.push(ConfigParam::Discrimination(Discrimination::Production));
+        ents.push(ConfigParam::Block0Date(Block0Date(1_556_202_057)));
+        let bytes = ents.serialize_as_vec().unwrap();
+
+        let decoded = InitialEnts::read(&mut ReadBuf::from(&bytes)).unwrap();
+        assert_eq!(decoded.block0_date(), Some(Block0Date(1_556_202_057)));
+        assert_eq!(decoded.discrimination(), Some(Discrimination::Production));
+        assert_eq!(decoded.consensus_version(), None);
+    }
+
+    #[test]
+    fn canonicalize_ignores_insertion_order() {
+        use chain_core::property::Serialize;
+
+        let params = [
+            ConfigParam::ConsensusVersion(ConsensusVersion::Bft),
+            ConfigParam::Block0Date(Block0Date(42)),
+            ConfigParam::Discrimination(Discrimination::Test),
+        ];
+        let mut ents1 = InitialEnts::new();
+        let mut ents2 = InitialEnts::new();
+        for param in params.iter() {
+            ents1.push(param.clone());
+        }
+        for param in params.iter().rev() {
+            ents2.push(param.clone());
+        }
+        assert_ne!(
+            ents1.serialize_as_vec().unwrap(),
+            ents2.serialize_as_vec().unwrap()
+        );
+
+        ents1.canonicalize();
+        ents2.canonicalize();
+        assert_eq!(ents1, ents2);
+        assert_eq!(
+            ents1.serialize_as_vec().unwrap(),
+            ents2.serialize_as_vec().unwrap()
+        );
+    }
+
+    #[test]
+    fn read_rejects_too_many_ents() {
+        use chain_core::property::Serialize;
+
+        let mut ents = InitialEnts::new();
+        for _ in 0..MAX_INITIAL_ENTS {
+            ents.push(ConfigParam::Discrimination(Discrimination::Test));
+        }
+        let bytes = ents.serialize_as_vec().unwrap();
+        assert_eq!(
+            InitialEnts::read(&mut ReadBuf::from(&bytes)),
+            Ok(ents.clone())
+        );
+
+        ents.push(ConfigParam::Discrimination(Discrimination::Test));
+        let bytes = ents.serialize_as_vec().unwrap();
+        assert_eq!(
+            InitialEnts::read(&mut ReadBuf::from(&bytes)),
+            Err(ReadError::SizeTooBig(
+                MAX_INITIAL_ENTS + 1,
+                MAX_INITIAL_ENTS
+            ))
+        );
+    }
+
+    #[test]
+    fn block0_config_missing_param() {
+        let mut ents = InitialEnts::new();
+        ents.push(ConfigParam::Block0Date(Block0Date(42)));
+        ents.push(ConfigParam::ConsensusVersion(ConsensusVersion::Bft));
+        assert_eq!(
+            Block0Config::from_initial_ents(&ents),
+            Err(InitialEntsError::MissingConfigParams(vec![
+                MissingParam::Discrimination
+            ]))
+        );
+
+        let err = Block0Config::from_initial_ents(&InitialEnts::new()).unwrap_err();
+        assert_eq!(
+            err,
+            InitialEntsError::MissingConfigParams(vec![
+                MissingParam::Block0Date,
+                MissingParam::Discrimination,
+                MissingParam::ConsensusVersion
+            ])
+        );
+        assert_eq!(
+            err.to_string(),
+            "missing config parameters: block0 date, discrimination, consensus version"
+        );
+    }
+
     quickcheck! {
+        fn block0_config_roundtrip(config: Block0Config) -> bool {
+            use chain_core::property::Serialize;
+
+            let ents = config.to_initial_ents();
+            let mut canonical = ents.clone();
+            canonical.canonicalize();
+            let bytes = ents.serialize_as_vec().unwrap();
+            let decoded = InitialEnts::read(&mut ReadBuf::from(&bytes)).unwrap();
+
+            canonical == ents && Block0Config::from_initial_ents(&decoded) == Ok(config)
+        }
+
+        fn initial_ents_canonical_bytes_stable(ents: InitialEnts) -> bool {
+            use chain_core::property::Serialize;
+
+            // the bytes of a canonical block0 initial message
+            let bytes = ents.into_canonical().serialize_as_vec().unwrap();
+            let decoded = InitialEnts::read(&mut ReadBuf::from(&bytes)).unwrap();
+            decoded.into_canonical().serialize_as_vec().unwrap() == bytes
+        }
+
         fn initial_ents_serialization_bijection(b: InitialEnts) -> TestResult {
             property::testing::serialization_bijection_r(b)
         }
     }
 
+    #[cfg(feature = "generic-serialization")]
+    quickcheck! {
+        fn initial_ents_json_roundtrip(ents: InitialEnts) -> bool {
+            let json = serde_json::to_value(&ents).unwrap();
+            let params: Vec<serde_json::Value> = ents
+                .iter()
+                .map(|param| serde_json::to_value(param).unwrap())
+                .collect();
+            json == serde_json::Value::Array(params)
+                && serde_json::from_value::<InitialEnts>(json).unwrap() == ents
+        }
+    }
+
+    #[cfg(feature = "generic-serialization")]
+    #[test]
+    fn initial_ents_json_is_array_of_params() {
+        let mut ents = InitialEnts::new();
+        ents.push(ConfigParam::Discrimination(Discrimination::Test));
+        ents.push(ConfigParam::ConsensusVersion(ConsensusVersion::Bft));
+        assert_eq!(
+            serde_json::to_value(&ents).unwrap(),
+            serde_json::json!([["discrimination", "test"], ["block0-consensus", "bft"]])
+        );
+    }
+
+    impl Arbitrary for Block0Config {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            Block0Config {
+                block0_date: Arbitrary::arbitrary(g),
+                discrimination: Arbitrary::arbitrary(g),
+                consensus_version: Arbitrary::arbitrary(g),
+            }
+        }
+    }
+
     impl Arbitrary for InitialEnts {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
             let size = u8::arbitrary(g) as usize;
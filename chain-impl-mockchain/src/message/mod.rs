@@ -8,8 +8,8 @@ use chain_core::property;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 
-pub use initial::InitialEnts;
-pub use raw::{MessageId, MessageRaw};
+pub use initial::{Block0Config, InitialEnts, InitialEntsError, MissingParam};
+pub use raw::{MessageId, MessageRaw, MAX_MESSAGE_SIZE};
 
 use crate::{
     certificate, setting,
@@ -26,9 +26,10 @@ pub enum Message {
     Update(setting::UpdateProposal),
 }
 
-/// Tag enumeration of all known message
-#[derive(Debug, Clone, Copy, FromPrimitive, PartialEq, Eq)]
-pub(super) enum MessageTag {
+/// Kind of a message, its value is the tag used in the serialization
+#[derive(Debug, Clone, Copy, FromPrimitive, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum MessageKind {
     Initial = 0,
     OldUtxoDeclaration = 1,
     Transaction = 2,
@@ -37,14 +38,14 @@ pub(super) enum MessageTag {
 }
 
 impl Message {
-    /// Return the tag associated with the Message
-    pub(super) fn get_tag(&self) -> MessageTag {
+    /// Return the kind of the Message, without inspecting its content
+    pub fn kind(&self) -> MessageKind {
         match self {
-            Message::Initial(_) => MessageTag::Initial,
-            Message::OldUtxoDeclaration(_) => MessageTag::OldUtxoDeclaration,
-            Message::Transaction(_) => MessageTag::Transaction,
-            Message::Certificate(_) => MessageTag::Certificate,
-            Message::Update(_) => MessageTag::Update,
+            Message::Initial(_) => MessageKind::Initial,
+            Message::OldUtxoDeclaration(_) => MessageKind::OldUtxoDeclaration,
+            Message::Transaction(_) => MessageKind::Transaction,
+            Message::Certificate(_) => MessageKind::Certificate,
+            Message::Update(_) => MessageKind::Update,
         }
     }
 
@@ -54,7 +55,7 @@ impl Message {
         use chain_core::property::Serialize;
         let v = Vec::new();
         let mut codec = Codec::from(v);
-        codec.put_u8(self.get_tag() as u8).unwrap();
+        codec.put_u8(self.kind() as u8).unwrap();
         match self {
             Message::Initial(i) => i.serialize(&mut codec).unwrap(),
             Message::OldUtxoDeclaration(s) => s.serialize(&mut codec).unwrap(),
@@ -65,21 +66,47 @@ impl Message {
         MessageRaw(codec.into_inner())
     }
 
+    /// Number of bytes the message adds to the contents of a block: its
+    /// serialized representation along with the size prefix, as counted
+    /// by `BlockContents::compute_hash_size`.
+    pub fn serialized_size(&self) -> usize {
+        self.to_raw().size_bytes_plus_size()
+    }
+
     pub fn from_raw(raw: &MessageRaw) -> Result<Self, ReadError> {
-        let buf = &mut ReadBuf::from(raw.as_ref());
+        Message::read(&mut ReadBuf::from(raw.as_ref()))
+    }
+}
+
+/// Anything that can be serialized as a `MessageRaw`, and so hashed as
+/// part of the contents of a block the same way as the built-in `Message`.
+pub trait ToMessageRaw {
+    fn to_raw(&self) -> MessageRaw;
+}
+
+impl ToMessageRaw for Message {
+    fn to_raw(&self) -> MessageRaw {
+        Message::to_raw(self)
+    }
+}
+
+/// Read the content of a `MessageRaw`, that is the message without its
+/// size prefix.
+impl Readable for Message {
+    fn read<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
         let tag = buf.get_u8()?;
-        match MessageTag::from_u8(tag) {
-            Some(MessageTag::Initial) => InitialEnts::read(buf).map(Message::Initial),
-            Some(MessageTag::OldUtxoDeclaration) => {
+        match MessageKind::from_u8(tag) {
+            Some(MessageKind::Initial) => InitialEnts::read(buf).map(Message::Initial),
+            Some(MessageKind::OldUtxoDeclaration) => {
                 legacy::UtxoDeclaration::read(buf).map(Message::OldUtxoDeclaration)
             }
-            Some(MessageTag::Transaction) => {
+            Some(MessageKind::Transaction) => {
                 AuthenticatedTransaction::read(buf).map(Message::Transaction)
             }
-            Some(MessageTag::Certificate) => {
+            Some(MessageKind::Certificate) => {
                 AuthenticatedTransaction::read(buf).map(Message::Certificate)
             }
-            Some(MessageTag::Update) => setting::UpdateProposal::read(buf).map(Message::Update),
+            Some(MessageKind::Update) => setting::UpdateProposal::read(buf).map(Message::Update),
             None => Err(ReadError::UnknownTag(tag as u32)),
         }
     }
@@ -113,7 +140,7 @@ impl property::Message for Message {
 #[cfg(test)]
 mod test {
     use super::*;
-    use quickcheck::{Arbitrary, Gen};
+    use quickcheck::{Arbitrary, Gen, StdThreadGen};
 
     impl Arbitrary for Message {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
@@ -126,4 +153,40 @@ mod test {
             }
         }
     }
+
+    quickcheck! {
+        fn message_kind_is_serialized_tag(message: Message) -> bool {
+            message.to_raw().as_ref()[0] == message.kind() as u8
+        }
+    }
+
+    #[test]
+    fn message_kind_of_each_variant() {
+        let mut g = StdThreadGen::new(10);
+        let messages = vec![
+            (
+                Message::Initial(Arbitrary::arbitrary(&mut g)),
+                MessageKind::Initial,
+            ),
+            (
+                Message::OldUtxoDeclaration(Arbitrary::arbitrary(&mut g)),
+                MessageKind::OldUtxoDeclaration,
+            ),
+            (
+                Message::Transaction(Arbitrary::arbitrary(&mut g)),
+                MessageKind::Transaction,
+            ),
+            (
+                Message::Certificate(Arbitrary::arbitrary(&mut g)),
+                MessageKind::Certificate,
+            ),
+            (
+                Message::Update(Arbitrary::arbitrary(&mut g)),
+                MessageKind::Update,
+            ),
+        ];
+        for (message, kind) in messages {
+            assert_eq!(message.kind(), kind);
+        }
+    }
 }
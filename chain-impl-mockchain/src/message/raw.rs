@@ -4,6 +4,9 @@ use chain_core::property;
 // FIXME: should this be a wrapper type?
 pub type MessageId = Hash;
 
+/// Maximum size of a serialized message, bounded by its `u16` size prefix
+pub const MAX_MESSAGE_SIZE: usize = u16::MAX as usize;
+
 /// A serialized Message
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MessageRaw(pub(super) Vec<u8>);
@@ -28,10 +31,19 @@ impl property::Deserialize for MessageRaw {
     type Error = std::io::Error;
     fn deserialize<R: std::io::BufRead>(reader: R) -> Result<Self, Self::Error> {
         use chain_core::packer::*;
+        use std::io::Read;
         let mut codec = Codec::from(reader);
-        let size = codec.get_u16()?;
-        let mut v = vec![0u8; size as usize];
-        codec.into_inner().read_exact(&mut v)?;
+        let size = codec.get_u16()? as usize;
+        // the buffer grows with the data actually read, so that a size
+        // prefix larger than the available input does not allocate upfront
+        let mut v = Vec::new();
+        codec.into_inner().take(size as u64).read_to_end(&mut v)?;
+        if v.len() != size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("message of {} bytes truncated to {} bytes", size, v.len()),
+            ));
+        }
         Ok(MessageRaw(v))
     }
 }
@@ -41,9 +53,44 @@ impl property::Serialize for MessageRaw {
     fn serialize<W: std::io::Write>(&self, writer: W) -> Result<(), Self::Error> {
         use chain_core::packer::*;
 
+        if self.0.len() > MAX_MESSAGE_SIZE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "message of {} bytes is bigger than the maximum of {} bytes",
+                    self.0.len(),
+                    MAX_MESSAGE_SIZE
+                ),
+            ));
+        }
+
         let mut codec = Codec::from(writer);
         codec.put_u16(self.0.len() as u16)?;
         codec.into_inner().write_all(&self.0)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chain_core::property::{Deserialize, Serialize};
+
+    #[test]
+    fn deserialize_size_bigger_than_input() {
+        let bytes = [0xff, 0xff, 1, 2, 3];
+        let err = MessageRaw::deserialize(&bytes[..]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn serialize_oversized_message() {
+        let raw = MessageRaw(vec![0; MAX_MESSAGE_SIZE + 1]);
+        let err = raw.serialize_as_vec().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let raw = MessageRaw(vec![0; MAX_MESSAGE_SIZE]);
+        let bytes = raw.serialize_as_vec().unwrap();
+        assert_eq!(MessageRaw::deserialize(&bytes[..]).unwrap(), raw);
+    }
+}
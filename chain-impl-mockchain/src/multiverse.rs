@@ -258,9 +258,12 @@ mod test {
     use super::Multiverse;
     use crate::block::{Block, BlockBuilder, ConsensusVersion};
     use crate::config::ConfigParam;
+    use crate::leadership::bft::LeaderId;
     use crate::ledger::Ledger;
     use crate::message::{InitialEnts, Message};
-    use chain_core::property::{Block as _, ChainLength as _, HasMessages as _};
+    use crate::setting::UpdateProposal;
+    use chain_core::property::{Block as _, HasMessages as _};
+    use chain_crypto::{Ed25519Extended, SecretKey};
     use chain_storage::store::BlockStore;
     use quickcheck::{Arbitrary, StdGen};
 
@@ -278,7 +281,7 @@ mod test {
         let mut multiverse = Multiverse::new();
 
         let mut g = StdGen::new(rand::thread_rng(), 10);
-        let leader_key = Arbitrary::arbitrary(&mut g);
+        let leader_key: SecretKey<Ed25519Extended> = Arbitrary::arbitrary(&mut g);
 
         let mut store = chain_storage::memory::MemoryBlockStore::new();
 
@@ -286,6 +289,9 @@ mod test {
         let mut ents = InitialEnts::new();
         ents.push(ConfigParam::ConsensusVersion(ConsensusVersion::Bft));
         genesis_block.message(Message::Initial(ents));
+        let mut update = UpdateProposal::new();
+        update.bft_leaders = Some(vec![LeaderId::from(leader_key.to_public())]);
+        genesis_block.message(Message::Update(update));
         let genesis_block = genesis_block.make_genesis_block();
         let genesis_state = Ledger::new(genesis_block.id(), genesis_block.messages()).unwrap();
         assert_eq!(genesis_state.chain_length().0, 0);
@@ -298,7 +304,7 @@ mod test {
         let mut ids = vec![];
         for i in 1..10001 {
             let mut block = BlockBuilder::new();
-            block.chain_length(state.chain_length.next());
+            block.chain_length(state.chain_length.next().unwrap());
             block.parent(parent);
             let block = block.make_bft_block(&leader_key);
             state = apply_block(&state, &block);
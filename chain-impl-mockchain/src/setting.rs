@@ -1,8 +1,15 @@
 //! define the Blockchain settings
 //!
 
-use crate::{block::ConsensusVersion, fee::LinearFee, key::Hash, leadership::bft};
-use chain_core::mempack::{read_vec, ReadBuf, ReadError, Readable};
+use crate::date::SlotId;
+use crate::{
+    block::ConsensusVersion,
+    fee::LinearFee,
+    key::Hash,
+    leadership::bft,
+    value::{Value, ValueError},
+};
+use chain_core::mempack::{ReadBuf, ReadError, Readable};
 use chain_core::property;
 use std::sync::Arc;
 
@@ -15,7 +22,7 @@ use num_traits::FromPrimitive;
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct UpdateProposal {
     pub max_number_of_transactions_per_block: Option<u32>,
-    pub bootstrap_key_slots_percentage: Option<u8>,
+    pub bootstrap_key_slots_percentage: Option<Percentage>,
     pub consensus_version: Option<ConsensusVersion>,
     pub bft_leaders: Option<Vec<bft::LeaderId>>,
     /// update to trigger allowing the creation of accounts without
@@ -27,6 +34,14 @@ pub struct UpdateProposal {
     pub slot_duration: Option<u8>,
     /// Todo
     pub epoch_stability_depth: Option<u32>,
+    /// update the ratio of the collected fees going to the treasury
+    pub treasury_tax: Option<TaxType>,
+    /// update the maximum size of the contents of a block, in bytes
+    pub max_block_content_size: Option<u32>,
+    /// update the maximum number of update proposals in a block
+    pub max_updates_per_block: Option<u32>,
+    /// update the maximum number of certificates in a block
+    pub max_certificates_per_block: Option<u32>,
 }
 
 impl UpdateProposal {
@@ -40,9 +55,171 @@ impl UpdateProposal {
             linear_fees: None,
             slot_duration: None,
             epoch_stability_depth: None,
+            treasury_tax: None,
+            max_block_content_size: None,
+            max_updates_per_block: None,
+            max_certificates_per_block: None,
+        }
+    }
+
+    /// tell whether the proposal changes a parameter the leader selection
+    /// or the chain selection depends on: the consensus version, the BFT
+    /// leaders, the bootstrap key slots percentage, the slot duration or
+    /// the epoch stability depth. Such changes should only take effect at
+    /// an epoch boundary.
+    pub fn is_consensus_critical(&self) -> bool {
+        self.consensus_version.is_some()
+            || self.bft_leaders.is_some()
+            || self.bootstrap_key_slots_percentage.is_some()
+            || self.slot_duration.is_some()
+            || self.epoch_stability_depth.is_some()
+    }
+
+    /// split the proposal in its consensus critical fields, as classified
+    /// by `is_consensus_critical`, and the other fields: returns
+    /// `(critical, non_critical)`. Applying both proposals, in any order,
+    /// is the same as applying the original one.
+    pub fn split_critical(&self) -> (UpdateProposal, UpdateProposal) {
+        let mut non_critical = self.clone();
+        let critical = UpdateProposal {
+            consensus_version: non_critical.consensus_version.take(),
+            bft_leaders: non_critical.bft_leaders.take(),
+            bootstrap_key_slots_percentage: non_critical.bootstrap_key_slots_percentage.take(),
+            slot_duration: non_critical.slot_duration.take(),
+            epoch_stability_depth: non_critical.epoch_stability_depth.take(),
+            ..UpdateProposal::new()
+        };
+        (critical, non_critical)
+    }
+}
+
+/// Ratio of the collected fees taken by the treasury, optionally capped
+/// to a maximum amount per epoch.
+///
+/// The ratio is always valid: the denominator is non-zero and the
+/// numerator is at most the denominator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaxType {
+    numerator: u64,
+    denominator: u64,
+    cap: Option<Value>,
+}
+
+impl TaxType {
+    pub fn new(numerator: u64, denominator: u64, cap: Option<Value>) -> Result<Self, TaxTypeError> {
+        if denominator == 0 {
+            return Err(TaxTypeError::ZeroDenominator);
+        }
+        if numerator > denominator {
+            return Err(TaxTypeError::RatioAboveOne(numerator, denominator));
+        }
+        Ok(TaxType {
+            numerator,
+            denominator,
+            cap,
+        })
+    }
+
+    /// no tax: nothing goes to the treasury
+    pub fn zero() -> Self {
+        TaxType {
+            numerator: 0,
+            denominator: 1,
+            cap: None,
+        }
+    }
+
+    pub fn numerator(&self) -> u64 {
+        self.numerator
+    }
+
+    pub fn denominator(&self) -> u64 {
+        self.denominator
+    }
+
+    pub fn cap(&self) -> Option<Value> {
+        self.cap
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaxTypeError {
+    ZeroDenominator,
+    RatioAboveOne(u64, u64),
+}
+impl std::fmt::Display for TaxTypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TaxTypeError::ZeroDenominator => write!(f, "Tax ratio has a zero denominator"),
+            TaxTypeError::RatioAboveOne(numerator, denominator) => write!(
+                f,
+                "Tax ratio {}/{} is greater than one",
+                numerator, denominator
+            ),
         }
     }
 }
+impl std::error::Error for TaxTypeError {}
+
+impl property::Serialize for TaxType {
+    type Error = std::io::Error;
+    fn serialize<W: std::io::Write>(&self, writer: W) -> Result<(), Self::Error> {
+        use chain_core::packer::Codec;
+        let mut codec = Codec::from(writer);
+        codec.put_u64(self.numerator)?;
+        codec.put_u64(self.denominator)?;
+        match self.cap {
+            None => codec.put_u8(0)?,
+            Some(cap) => {
+                codec.put_u8(1)?;
+                codec.put_u64(cap.0)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Readable for TaxType {
+    fn read<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+        let numerator = buf.get_u64()?;
+        let denominator = buf.get_u64()?;
+        let cap = match buf.get_u8()? {
+            0 => None,
+            1 => Some(Value(buf.get_u64()?)),
+            t => {
+                return Err(ReadError::StructureInvalid(format!(
+                    "Invalid treasury tax cap tag {}",
+                    t
+                )))
+            }
+        };
+        TaxType::new(numerator, denominator, cap)
+            .map_err(|e| ReadError::StructureInvalid(e.to_string()))
+    }
+}
+
+fn read_percentage<'a>(buf: &mut ReadBuf<'a>) -> Result<Percentage, ReadError> {
+    let percentage_u8 = buf.get_u8()?;
+    Percentage::new(percentage_u8).ok_or_else(|| {
+        ReadError::StructureInvalid(format!(
+            "Invalid bootstrap key slots percentage {}",
+            percentage_u8
+        ))
+    })
+}
+
+fn read_consensus_version<'a>(buf: &mut ReadBuf<'a>) -> Result<ConsensusVersion, ReadError> {
+    let version_u16 = buf.get_u16()?;
+    ConsensusVersion::from_u16(version_u16).ok_or_else(|| {
+        ReadError::StructureInvalid(format!("Unrecognized consensus version {}", version_u16))
+    })
+}
+
+/// Tags from this value up are reserved for fields unknown to this
+/// version of the protocol: they are followed by the size of their value
+/// as a `u16`, so readers can skip them. Unknown tags below this value
+/// are rejected.
+pub const UPDATE_TAG_SKIPPABLE_START: u16 = 0x8000;
 
 #[derive(FromPrimitive)]
 enum UpdateTag {
@@ -55,6 +232,10 @@ enum UpdateTag {
     LinearFee = 6,
     SlotDuration = 7,
     EpochStabilityDepth = 8,
+    TreasuryTax = 9,
+    MaxBlockContentSize = 10,
+    MaxUpdatesPerBlock = 11,
+    MaxCertificatesPerBlock = 12,
 }
 
 impl property::Serialize for UpdateProposal {
@@ -70,7 +251,7 @@ impl property::Serialize for UpdateProposal {
         }
         if let Some(bootstrap_key_slots_percentage) = self.bootstrap_key_slots_percentage {
             codec.put_u16(UpdateTag::BootstrapKeySlotsPercentage as u16)?;
-            codec.put_u8(bootstrap_key_slots_percentage)?;
+            codec.put_u8(bootstrap_key_slots_percentage.as_u8())?;
         }
         if let Some(consensus_version) = self.consensus_version {
             codec.put_u16(UpdateTag::ConsensusVersion as u16)?;
@@ -89,9 +270,7 @@ impl property::Serialize for UpdateProposal {
         }
         if let Some(linear_fees) = &self.linear_fees {
             codec.put_u16(UpdateTag::LinearFee as u16)?;
-            codec.put_u64(linear_fees.constant)?;
-            codec.put_u64(linear_fees.coefficient)?;
-            codec.put_u64(linear_fees.certificate)?;
+            linear_fees.serialize(&mut codec)?;
         }
         if let Some(slot_duration) = self.slot_duration {
             codec.put_u16(UpdateTag::SlotDuration as u16)?;
@@ -101,13 +280,29 @@ impl property::Serialize for UpdateProposal {
             codec.put_u16(UpdateTag::EpochStabilityDepth as u16)?;
             codec.put_u32(epoch_stability_depth)?;
         }
+        if let Some(treasury_tax) = &self.treasury_tax {
+            codec.put_u16(UpdateTag::TreasuryTax as u16)?;
+            treasury_tax.serialize(&mut codec)?;
+        }
+        if let Some(max_block_content_size) = self.max_block_content_size {
+            codec.put_u16(UpdateTag::MaxBlockContentSize as u16)?;
+            codec.put_u32(max_block_content_size)?;
+        }
+        if let Some(max_updates_per_block) = self.max_updates_per_block {
+            codec.put_u16(UpdateTag::MaxUpdatesPerBlock as u16)?;
+            codec.put_u32(max_updates_per_block)?;
+        }
+        if let Some(max_certificates_per_block) = self.max_certificates_per_block {
+            codec.put_u16(UpdateTag::MaxCertificatesPerBlock as u16)?;
+            codec.put_u32(max_certificates_per_block)?;
+        }
         codec.put_u16(UpdateTag::End as u16)?;
         Ok(())
     }
 }
 
-impl Readable for UpdateProposal {
-    fn read<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+impl UpdateProposal {
+    fn read_unlocated<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
         let mut update = UpdateProposal::new();
         loop {
             let tag = buf.get_u16()?;
@@ -119,21 +314,20 @@ impl Readable for UpdateProposal {
                     update.max_number_of_transactions_per_block = Some(buf.get_u32()?);
                 }
                 Some(UpdateTag::BootstrapKeySlotsPercentage) => {
-                    update.bootstrap_key_slots_percentage = Some(buf.get_u8()?);
+                    update.bootstrap_key_slots_percentage = Some(read_percentage(buf)?);
                 }
                 Some(UpdateTag::ConsensusVersion) => {
-                    let version_u16 = buf.get_u16()?;
-                    let version = ConsensusVersion::from_u16(version_u16).ok_or_else(|| {
-                        ReadError::StructureInvalid(format!(
-                            "Unrecognized consensus version {}",
-                            version_u16
-                        ))
-                    })?;
-                    update.consensus_version = Some(version);
+                    update.consensus_version = Some(read_consensus_version(buf)?);
                 }
                 Some(UpdateTag::BftLeaders) => {
+                    // read the leaders one by one instead of allocating for
+                    // the declared count upfront, so a truncated buffer fails
+                    // on the first missing leader
                     let len = buf.get_u8()? as usize;
-                    let leaders = read_vec(buf, len)?;
+                    let mut leaders = Vec::new();
+                    for _ in 0..len {
+                        leaders.push(bft::LeaderId::read(buf)?);
+                    }
                     update.bft_leaders = Some(leaders);
                 }
                 Some(UpdateTag::AllowAccountCreation) => {
@@ -141,11 +335,7 @@ impl Readable for UpdateProposal {
                     update.allow_account_creation = Some(boolean);
                 }
                 Some(UpdateTag::LinearFee) => {
-                    update.linear_fees = Some(LinearFee {
-                        constant: buf.get_u64()?,
-                        coefficient: buf.get_u64()?,
-                        certificate: buf.get_u64()?,
-                    });
+                    update.linear_fees = Some(LinearFee::read(buf)?);
                 }
                 Some(UpdateTag::SlotDuration) => {
                     update.slot_duration = Some(buf.get_u8()?);
@@ -153,38 +343,90 @@ impl Readable for UpdateProposal {
                 Some(UpdateTag::EpochStabilityDepth) => {
                     update.epoch_stability_depth = Some(buf.get_u32()?);
                 }
-                None => panic!("Unrecognized update tag {}.", tag),
+                Some(UpdateTag::TreasuryTax) => {
+                    update.treasury_tax = Some(TaxType::read(buf)?);
+                }
+                Some(UpdateTag::MaxBlockContentSize) => {
+                    update.max_block_content_size = Some(buf.get_u32()?);
+                }
+                Some(UpdateTag::MaxUpdatesPerBlock) => {
+                    update.max_updates_per_block = Some(buf.get_u32()?);
+                }
+                Some(UpdateTag::MaxCertificatesPerBlock) => {
+                    update.max_certificates_per_block = Some(buf.get_u32()?);
+                }
+                None if tag >= UPDATE_TAG_SKIPPABLE_START => {
+                    let size = buf.get_u16()?;
+                    buf.skip_bytes(size as usize)?;
+                }
+                None => return Err(ReadError::UnknownTag(tag as u32)),
             }
         }
     }
 }
 
+/// Errors are located at the offset of the read that failed
+impl Readable for UpdateProposal {
+    fn read<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+        Self::read_unlocated(buf).map_err(|e| buf.locate_error(e))
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Settings {
     pub max_number_of_transactions_per_block: u32,
-    pub bootstrap_key_slots_percentage: u8, // == d * 100
+    pub bootstrap_key_slots_percentage: Percentage, // == d * 100
     pub consensus_version: ConsensusVersion,
     pub bft_leaders: Arc<Vec<bft::LeaderId>>,
     /// allow for the creation of accounts without the certificate
     pub allow_account_creation: bool,
     pub linear_fees: Arc<LinearFee>,
     pub slot_duration: u8,
-    pub epoch_stability_depth: usize,
+    pub epoch_stability_depth: u32,
+    pub treasury_tax: TaxType,
+    pub max_block_content_size: u32,
+    /// maximum number of update proposals in a block
+    pub max_updates_per_block: u32,
+    /// maximum number of certificates in a block
+    pub max_certificates_per_block: u32,
 }
 
 pub const SLOTS_PERCENTAGE_RANGE: u8 = 100;
 
+/// A percentage, guaranteed to be within `0..=SLOTS_PERCENTAGE_RANGE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Percentage(u8);
+
+impl Percentage {
+    /// returns `None` if the value is greater than `SLOTS_PERCENTAGE_RANGE`
+    pub fn new(value: u8) -> Option<Self> {
+        if value > SLOTS_PERCENTAGE_RANGE {
+            None
+        } else {
+            Some(Percentage(value))
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        self.0
+    }
+}
+
 impl Settings {
     pub fn new() -> Self {
         Self {
             max_number_of_transactions_per_block: 100,
-            bootstrap_key_slots_percentage: SLOTS_PERCENTAGE_RANGE,
+            bootstrap_key_slots_percentage: Percentage(SLOTS_PERCENTAGE_RANGE),
             consensus_version: ConsensusVersion::Bft,
             bft_leaders: Arc::new(Vec::new()),
             allow_account_creation: false,
             linear_fees: Arc::new(LinearFee::new(0, 0, 0)),
             slot_duration: 10,         // 10 sec
             epoch_stability_depth: 10, // num of block
+            treasury_tax: TaxType::zero(),
+            max_block_content_size: 102_400, // 100 KiB
+            max_updates_per_block: u32::MAX,
+            max_certificates_per_block: u32::MAX,
         }
     }
 
@@ -196,6 +438,48 @@ impl Settings {
         *self.linear_fees
     }
 
+    /// the BFT leader the slot is assigned to, `None` if the slot is not
+    /// a bootstrap slot or if there are no BFT leaders.
+    ///
+    /// In every run of `SLOTS_PERCENTAGE_RANGE` slots, the first
+    /// `bootstrap_key_slots_percentage` ones are bootstrap slots. They are
+    /// assigned round-robin to the BFT leaders, following the slot id as
+    /// the BFT leader selection does.
+    pub fn bft_leader_for_slot(&self, slot: SlotId) -> Option<&bft::LeaderId> {
+        if self.bft_leaders.is_empty()
+            || slot % SlotId::from(SLOTS_PERCENTAGE_RANGE)
+                >= SlotId::from(self.bootstrap_key_slots_percentage.as_u8())
+        {
+            return None;
+        }
+        self.bft_leaders.get(slot as usize % self.bft_leaders.len())
+    }
+
+    /// the `d` parameter, the ratio of slots assigned to the bootstrap
+    /// keys, between 0 and 1
+    pub fn d_parameter(&self) -> f64 {
+        f64::from(self.bootstrap_key_slots_percentage.as_u8()) / f64::from(SLOTS_PERCENTAGE_RANGE)
+    }
+
+    /// split the given amount of fees between the treasury and the
+    /// rewards, following the treasury tax: returns the treasury's part
+    /// and the remainder.
+    ///
+    /// The treasury's part is rounded down, so the rounding error goes to
+    /// the remainder, and is then limited to the tax's cap, if any.
+    pub fn split_treasury(&self, total: Value) -> Result<(Value, Value), ValueError> {
+        let tax = &self.treasury_tax;
+        // the ratio is at most one, so the result fits in a u64
+        let treasury =
+            u128::from(total.0) * u128::from(tax.numerator) / u128::from(tax.denominator);
+        let mut treasury = Value(treasury as u64);
+        if let Some(cap) = tax.cap {
+            treasury = std::cmp::min(treasury, cap);
+        }
+        let remainder = (total - treasury)?;
+        Ok((treasury, remainder))
+    }
+
     pub fn apply(&self, update: &UpdateProposal) -> Self {
         let mut new_state = self.clone();
         if let Some(max_number_of_transactions_per_block) =
@@ -222,13 +506,202 @@ impl Settings {
             new_state.slot_duration = slot_duration;
         }
         if let Some(epoch_stability_depth) = update.epoch_stability_depth {
-            new_state.epoch_stability_depth = epoch_stability_depth as usize;
+            new_state.epoch_stability_depth = epoch_stability_depth;
+        }
+        if let Some(treasury_tax) = update.treasury_tax {
+            new_state.treasury_tax = treasury_tax;
+        }
+        if let Some(max_block_content_size) = update.max_block_content_size {
+            new_state.max_block_content_size = max_block_content_size;
+        }
+        if let Some(max_updates_per_block) = update.max_updates_per_block {
+            new_state.max_updates_per_block = max_updates_per_block;
+        }
+        if let Some(max_certificates_per_block) = update.max_certificates_per_block {
+            new_state.max_certificates_per_block = max_certificates_per_block;
         }
         new_state
     }
+
+    /// check the settings against the following invariants:
+    ///
+    /// * `max_number_of_transactions_per_block` is not zero, otherwise
+    ///   no transaction could be included in a block anymore;
+    /// * `bft_leaders` is not empty when `consensus_version` is BFT,
+    ///   otherwise nobody could create the next block;
+    /// * `bft_leaders` does not list the same leader twice, otherwise
+    ///   that leader would be assigned more slots than the others.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.max_number_of_transactions_per_block == 0 {
+            return Err(Error::UpdateIsInvalid);
+        }
+        if self.consensus_version == ConsensusVersion::Bft && self.bft_leaders.is_empty() {
+            return Err(Error::UpdateIsInvalid);
+        }
+        let leaders = &self.bft_leaders;
+        if leaders
+            .iter()
+            .enumerate()
+            .any(|(i, leader)| leaders[..i].contains(leader))
+        {
+            return Err(Error::UpdateIsInvalid);
+        }
+        Ok(())
+    }
+
+    /// same as `apply`, but reject the update if the resulting settings
+    /// would break one of the invariants checked by `validate`
+    pub fn try_apply(&self, update: &UpdateProposal) -> Result<Self, Error> {
+        let new_state = self.apply(update);
+        new_state.validate()?;
+        Ok(new_state)
+    }
+
+    /// describe, one entry per field, what applying the given update
+    /// would change. Fields left unset by the update or set to their
+    /// current value are skipped.
+    pub fn describe_changes(&self, update: &UpdateProposal) -> Vec<String> {
+        let mut changes = Vec::new();
+        macro_rules! describe {
+            ($field:ident, $current:expr, $new:expr) => {
+                if let Some(new) = $new {
+                    if new != $current {
+                        changes.push(format!(
+                            "{}: {:?} -> {:?}",
+                            stringify!($field),
+                            $current,
+                            new
+                        ));
+                    }
+                }
+            };
+        }
+        describe!(
+            max_number_of_transactions_per_block,
+            self.max_number_of_transactions_per_block,
+            update.max_number_of_transactions_per_block
+        );
+        describe!(
+            bootstrap_key_slots_percentage,
+            self.bootstrap_key_slots_percentage.as_u8(),
+            update.bootstrap_key_slots_percentage.map(Percentage::as_u8)
+        );
+        describe!(
+            consensus_version,
+            self.consensus_version,
+            update.consensus_version
+        );
+        describe!(bft_leaders, *self.bft_leaders, update.bft_leaders.clone());
+        describe!(
+            allow_account_creation,
+            self.allow_account_creation,
+            update.allow_account_creation
+        );
+        describe!(linear_fees, *self.linear_fees, update.linear_fees);
+        describe!(slot_duration, self.slot_duration, update.slot_duration);
+        describe!(
+            epoch_stability_depth,
+            self.epoch_stability_depth,
+            update.epoch_stability_depth
+        );
+        describe!(treasury_tax, self.treasury_tax, update.treasury_tax);
+        describe!(
+            max_block_content_size,
+            self.max_block_content_size,
+            update.max_block_content_size
+        );
+        describe!(
+            max_updates_per_block,
+            self.max_updates_per_block,
+            update.max_updates_per_block
+        );
+        describe!(
+            max_certificates_per_block,
+            self.max_certificates_per_block,
+            update.max_certificates_per_block
+        );
+        changes
+    }
+
+    /// apply the given updates in order, later updates overriding the
+    /// fields set by the earlier ones
+    pub fn apply_all<'a, I: IntoIterator<Item = &'a UpdateProposal>>(&self, updates: I) -> Self {
+        updates
+            .into_iter()
+            .fold(self.clone(), |settings, update| settings.apply(update))
+    }
+}
+
+/// Every field is written, in declaration order, so a snapshot of the
+/// settings can be restored without replaying the updates.
+impl property::Serialize for Settings {
+    type Error = std::io::Error;
+    fn serialize<W: std::io::Write>(&self, writer: W) -> Result<(), Self::Error> {
+        use chain_core::packer::Codec;
+
+        if self.bft_leaders.len() > u8::MAX as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "too many BFT leaders".to_string(),
+            ));
+        }
+
+        let mut codec = Codec::from(writer);
+        codec.put_u32(self.max_number_of_transactions_per_block)?;
+        codec.put_u8(self.bootstrap_key_slots_percentage.as_u8())?;
+        codec.put_u16(self.consensus_version as u16)?;
+        codec.put_u8(self.bft_leaders.len() as u8)?;
+        for leader in self.bft_leaders.iter() {
+            leader.serialize(&mut codec)?;
+        }
+        codec.put_u8(if self.allow_account_creation { 1 } else { 0 })?;
+        self.linear_fees.serialize(&mut codec)?;
+        codec.put_u8(self.slot_duration)?;
+        codec.put_u32(self.epoch_stability_depth)?;
+        self.treasury_tax.serialize(&mut codec)?;
+        codec.put_u32(self.max_block_content_size)?;
+        codec.put_u32(self.max_updates_per_block)?;
+        codec.put_u32(self.max_certificates_per_block)?;
+        Ok(())
+    }
+}
+
+impl Readable for Settings {
+    fn read<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+        let max_number_of_transactions_per_block = buf.get_u32()?;
+        let bootstrap_key_slots_percentage = read_percentage(buf)?;
+        let consensus_version = read_consensus_version(buf)?;
+        let leaders_nb = buf.get_u8()? as usize;
+        let mut bft_leaders = Vec::new();
+        for _ in 0..leaders_nb {
+            bft_leaders.push(bft::LeaderId::read(buf)?);
+        }
+        let allow_account_creation = buf.get_u8()? != 0;
+        let linear_fees = LinearFee::read(buf)?;
+        let slot_duration = buf.get_u8()?;
+        let epoch_stability_depth = buf.get_u32()?;
+        let treasury_tax = TaxType::read(buf)?;
+        let max_block_content_size = buf.get_u32()?;
+        let max_updates_per_block = buf.get_u32()?;
+        let max_certificates_per_block = buf.get_u32()?;
+        Ok(Settings {
+            max_number_of_transactions_per_block,
+            bootstrap_key_slots_percentage,
+            consensus_version,
+            bft_leaders: Arc::new(bft_leaders),
+            allow_account_creation,
+            linear_fees: Arc::new(linear_fees),
+            slot_duration,
+            epoch_stability_depth,
+            treasury_tax,
+            max_block_content_size,
+            max_updates_per_block,
+            max_certificates_per_block,
+        })
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Error {
     InvalidCurrentBlockId(Hash, Hash),
     UpdateIsInvalid,
@@ -251,7 +724,22 @@ impl std::error::Error for Error {}
 #[cfg(test)]
 mod test {
     use super::*;
-    use quickcheck::{Arbitrary, Gen};
+    use chain_core::property::{testing::serialization_bijection_r, Serialize as _};
+    use quickcheck::{Arbitrary, Gen, TestResult};
+
+    impl Arbitrary for Percentage {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            Percentage(u8::arbitrary(g) % (SLOTS_PERCENTAGE_RANGE + 1))
+        }
+    }
+
+    impl Arbitrary for TaxType {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            let denominator = u64::arbitrary(g).saturating_add(1);
+            let numerator = u64::arbitrary(g) % (denominator + 1);
+            TaxType::new(numerator, denominator, Arbitrary::arbitrary(g)).unwrap()
+        }
+    }
 
     impl Arbitrary for UpdateProposal {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
@@ -261,10 +749,400 @@ mod test {
                 consensus_version: Arbitrary::arbitrary(g),
                 bft_leaders: None,
                 allow_account_creation: None,
-                linear_fees: None,
+                linear_fees: Arbitrary::arbitrary(g),
                 slot_duration: Arbitrary::arbitrary(g),
                 epoch_stability_depth: Arbitrary::arbitrary(g),
+                treasury_tax: Arbitrary::arbitrary(g),
+                max_block_content_size: Arbitrary::arbitrary(g),
+                max_updates_per_block: Arbitrary::arbitrary(g),
+                max_certificates_per_block: Arbitrary::arbitrary(g),
             }
         }
     }
+
+    impl Arbitrary for Settings {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            let leaders_nb = usize::arbitrary(g) % 4;
+            Settings {
+                max_number_of_transactions_per_block: Arbitrary::arbitrary(g),
+                bootstrap_key_slots_percentage: Arbitrary::arbitrary(g),
+                consensus_version: Arbitrary::arbitrary(g),
+                bft_leaders: Arc::new(
+                    std::iter::repeat_with(|| Arbitrary::arbitrary(g))
+                        .take(leaders_nb)
+                        .collect(),
+                ),
+                allow_account_creation: Arbitrary::arbitrary(g),
+                linear_fees: Arc::new(Arbitrary::arbitrary(g)),
+                slot_duration: Arbitrary::arbitrary(g),
+                epoch_stability_depth: Arbitrary::arbitrary(g),
+                treasury_tax: Arbitrary::arbitrary(g),
+                max_block_content_size: Arbitrary::arbitrary(g),
+                max_updates_per_block: Arbitrary::arbitrary(g),
+                max_certificates_per_block: Arbitrary::arbitrary(g),
+            }
+        }
+    }
+
+    quickcheck! {
+        fn update_proposal_serialization_bijection(b: UpdateProposal) -> TestResult {
+            serialization_bijection_r(b)
+        }
+
+        fn settings_serialization_bijection(settings: Settings) -> TestResult {
+            serialization_bijection_r(settings)
+        }
+
+        fn update_proposal_split_critical(
+            update: UpdateProposal,
+            bft_leaders: Option<Vec<bft::LeaderId>>
+        ) -> bool {
+            let mut update = update;
+            update.bft_leaders = bft_leaders;
+            let (critical, non_critical) = update.split_critical();
+            let settings = Settings::new();
+            let expected = settings.apply(&update);
+
+            critical.is_consensus_critical() == update.is_consensus_critical()
+                && !non_critical.is_consensus_critical()
+                && settings.apply(&critical).apply(&non_critical) == expected
+                && settings.apply(&non_critical).apply(&critical) == expected
+        }
+    }
+
+    #[test]
+    fn percentage_boundaries() {
+        assert_eq!(Percentage::new(0).map(Percentage::as_u8), Some(0));
+        assert_eq!(
+            Percentage::new(SLOTS_PERCENTAGE_RANGE).map(Percentage::as_u8),
+            Some(SLOTS_PERCENTAGE_RANGE)
+        );
+        assert_eq!(Percentage::new(101), None);
+        assert_eq!(Percentage::new(u8::MAX), None);
+    }
+
+    #[test]
+    fn update_proposal_is_consensus_critical() {
+        let mut update = UpdateProposal::new();
+        assert!(!update.is_consensus_critical());
+        update.linear_fees = Some(LinearFee::new(1, 2, 3));
+        update.max_block_content_size = Some(4096);
+        assert!(!update.is_consensus_critical());
+
+        let mut consensus_version = update.clone();
+        consensus_version.consensus_version = Some(ConsensusVersion::GenesisPraos);
+        assert!(consensus_version.is_consensus_critical());
+
+        let mut slot_duration = update.clone();
+        slot_duration.slot_duration = Some(20);
+        assert!(slot_duration.is_consensus_critical());
+
+        let mut bft_leaders = update;
+        bft_leaders.bft_leaders = Some(Vec::new());
+        assert!(bft_leaders.is_consensus_critical());
+    }
+
+    #[test]
+    fn update_proposal_rejects_truncated_bft_leaders() {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let leader: bft::LeaderId = Arbitrary::arbitrary(&mut g);
+        let mut update = UpdateProposal::new();
+        update.bft_leaders = Some(vec![leader]);
+        let mut bytes = update.serialize_as_vec().unwrap();
+        // tag (u16) followed by the number of leaders
+        assert_eq!(bytes[2], 1);
+        bytes[2] = u8::MAX;
+
+        // the read fails when reaching the end of the buffer in the
+        // second leader
+        match UpdateProposal::read(&mut ReadBuf::from(&bytes)) {
+            Err(ReadError::AtPosition(position, _)) => assert_eq!(position, bytes.len()),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn update_proposal_skips_skippable_fields() {
+        let mut update = UpdateProposal::new();
+        update.slot_duration = Some(20);
+        update.max_block_content_size = Some(1024);
+        let bytes = update.serialize_as_vec().unwrap();
+
+        // insert a skippable field with a 3 bytes value after the first field
+        let first_field_size = 2 + 1;
+        let mut extended = bytes[..first_field_size].to_vec();
+        extended.extend_from_slice(&UPDATE_TAG_SKIPPABLE_START.to_be_bytes());
+        extended.extend_from_slice(&3u16.to_be_bytes());
+        extended.extend_from_slice(&[0xff, 0xff, 0xff]);
+        extended.extend_from_slice(&bytes[first_field_size..]);
+
+        let mut buf = ReadBuf::from(&extended);
+        assert_eq!(UpdateProposal::read(&mut buf), Ok(update));
+        assert!(buf.expect_end().is_ok());
+    }
+
+    #[test]
+    fn update_proposal_rejects_unknown_tag() {
+        let mut bytes = UPDATE_TAG_SKIPPABLE_START.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&10u16.to_be_bytes());
+        // the skippable field is truncated
+        assert!(UpdateProposal::read(&mut ReadBuf::from(&bytes)).is_err());
+
+        let tag = UPDATE_TAG_SKIPPABLE_START - 1;
+        let mut bytes = tag.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&0u16.to_be_bytes());
+        assert_eq!(
+            UpdateProposal::read(&mut ReadBuf::from(&bytes)),
+            Err(ReadError::AtPosition(
+                0,
+                Box::new(ReadError::UnknownTag(tag as u32))
+            ))
+        );
+    }
+
+    fn settings_with_bft_leaders(percentage: u8, leaders_nb: usize) -> Settings {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let mut settings = Settings::new();
+        settings.bootstrap_key_slots_percentage = Percentage::new(percentage).unwrap();
+        settings.bft_leaders = Arc::new(
+            std::iter::repeat_with(|| Arbitrary::arbitrary(&mut g))
+                .take(leaders_nb)
+                .collect(),
+        );
+        settings
+    }
+
+    #[test]
+    fn bft_leader_for_slot_full_bootstrap() {
+        let settings = settings_with_bft_leaders(SLOTS_PERCENTAGE_RANGE, 3);
+        for slot in 0..1000 {
+            assert_eq!(
+                settings.bft_leader_for_slot(slot),
+                Some(&settings.bft_leaders[slot as usize % 3])
+            );
+        }
+    }
+
+    #[test]
+    fn bft_leader_for_slot_partial_bootstrap() {
+        let settings = settings_with_bft_leaders(20, 3);
+        let bootstrap_slots = (0..1000)
+            .filter(|slot| settings.bft_leader_for_slot(*slot).is_some())
+            .count();
+        assert_eq!(bootstrap_slots, 200);
+        assert!(settings.bft_leader_for_slot(119).is_some());
+        assert!(settings.bft_leader_for_slot(120).is_none());
+    }
+
+    #[test]
+    fn bft_leader_for_slot_no_leaders() {
+        for percentage in &[0, 50, SLOTS_PERCENTAGE_RANGE] {
+            let settings = settings_with_bft_leaders(*percentage, 0);
+            assert!((0..1000).all(|slot| settings.bft_leader_for_slot(slot).is_none()));
+        }
+        let settings = settings_with_bft_leaders(0, 3);
+        assert!((0..1000).all(|slot| settings.bft_leader_for_slot(slot).is_none()));
+    }
+
+    #[test]
+    fn settings_d_parameter() {
+        let mut settings = Settings::new();
+        for (percentage, d) in &[(0, 0.0), (20, 0.2), (50, 0.5), (100, 1.0)] {
+            settings.bootstrap_key_slots_percentage = Percentage::new(*percentage).unwrap();
+            assert_eq!(settings.d_parameter(), *d);
+        }
+    }
+
+    #[test]
+    fn update_proposal_rejects_invalid_percentage() {
+        let mut update = UpdateProposal::new();
+        update.bootstrap_key_slots_percentage = Percentage::new(SLOTS_PERCENTAGE_RANGE);
+        let mut bytes = update.serialize_as_vec().unwrap();
+        // tag (u16) followed by the percentage byte
+        assert_eq!(bytes[2], SLOTS_PERCENTAGE_RANGE);
+        bytes[2] = 101;
+        let mut buf = ReadBuf::from(&bytes);
+        // the error is located at the corrupted percentage byte
+        match UpdateProposal::read(&mut buf) {
+            Err(ReadError::AtPosition(2, ref e)) => match **e {
+                ReadError::StructureInvalid(_) => {}
+                _ => panic!("unexpected error {:?}", e),
+            },
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn tax_type_rejects_invalid_ratio() {
+        assert_eq!(TaxType::new(1, 0, None), Err(TaxTypeError::ZeroDenominator));
+        assert_eq!(
+            TaxType::new(3, 2, None),
+            Err(TaxTypeError::RatioAboveOne(3, 2))
+        );
+        assert!(TaxType::new(2, 2, Some(Value(10))).is_ok());
+    }
+
+    quickcheck! {
+        fn apply_all_is_sequential_apply(a: UpdateProposal, b: UpdateProposal) -> bool {
+            let settings = Settings::new();
+            settings.apply_all(&[a.clone(), b.clone()]) == settings.apply(&a).apply(&b)
+        }
+    }
+
+    #[test]
+    fn describe_changes() {
+        let settings = Settings::new();
+        let mut update = UpdateProposal::new();
+        update.slot_duration = Some(20);
+        update.allow_account_creation = Some(true);
+        update.epoch_stability_depth = Some(settings.epoch_stability_depth);
+        assert_eq!(
+            settings.describe_changes(&update),
+            vec![
+                "allow_account_creation: false -> true".to_string(),
+                "slot_duration: 10 -> 20".to_string(),
+            ]
+        );
+        assert!(settings.describe_changes(&UpdateProposal::new()).is_empty());
+    }
+
+    fn bft_settings() -> Settings {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let mut update = UpdateProposal::new();
+        update.consensus_version = Some(ConsensusVersion::Bft);
+        update.bft_leaders = Some(vec![Arbitrary::arbitrary(&mut g)]);
+        Settings::new().apply(&update)
+    }
+
+    #[test]
+    fn try_apply_valid_update() {
+        let settings = bft_settings();
+        let mut update = UpdateProposal::new();
+        update.max_number_of_transactions_per_block = Some(1);
+        assert_eq!(settings.try_apply(&update), Ok(settings.apply(&update)));
+    }
+
+    #[test]
+    fn try_apply_rejects_zero_transactions_per_block() {
+        let mut update = UpdateProposal::new();
+        update.max_number_of_transactions_per_block = Some(0);
+        assert_eq!(
+            bft_settings().try_apply(&update),
+            Err(Error::UpdateIsInvalid)
+        );
+    }
+
+    #[test]
+    fn try_apply_rejects_empty_bft_leaders() {
+        let mut update = UpdateProposal::new();
+        update.bft_leaders = Some(Vec::new());
+        assert_eq!(
+            bft_settings().try_apply(&update),
+            Err(Error::UpdateIsInvalid)
+        );
+
+        update.consensus_version = Some(ConsensusVersion::GenesisPraos);
+        assert!(bft_settings().try_apply(&update).is_ok());
+    }
+
+    #[test]
+    fn try_apply_rejects_duplicate_bft_leaders() {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let leader1: bft::LeaderId = Arbitrary::arbitrary(&mut g);
+        let leader2: bft::LeaderId = Arbitrary::arbitrary(&mut g);
+        let mut update = UpdateProposal::new();
+
+        update.bft_leaders = Some(vec![leader1.clone(), leader2.clone()]);
+        assert!(bft_settings().try_apply(&update).is_ok());
+
+        update.bft_leaders = Some(vec![leader1.clone(), leader2, leader1]);
+        assert_eq!(
+            bft_settings().try_apply(&update),
+            Err(Error::UpdateIsInvalid)
+        );
+    }
+
+    #[test]
+    fn apply_epoch_stability_depth() {
+        let mut update = UpdateProposal::new();
+        update.epoch_stability_depth = Some(u32::MAX);
+        let settings = Settings::new().apply(&update);
+        assert_eq!(settings.epoch_stability_depth, u32::MAX);
+    }
+
+    #[test]
+    fn apply_max_block_content_size() {
+        let mut update = UpdateProposal::new();
+        update.max_block_content_size = Some(4096);
+        let bytes = update.serialize_as_vec().unwrap();
+        let decoded = UpdateProposal::read(&mut ReadBuf::from(&bytes)).unwrap();
+        assert_eq!(decoded, update);
+
+        let settings = Settings::new().apply(&decoded);
+        assert_eq!(settings.max_block_content_size, 4096);
+    }
+
+    #[test]
+    fn apply_max_messages_per_block() {
+        let mut update = UpdateProposal::new();
+        update.max_updates_per_block = Some(2);
+        update.max_certificates_per_block = Some(3);
+        let bytes = update.serialize_as_vec().unwrap();
+        let decoded = UpdateProposal::read(&mut ReadBuf::from(&bytes)).unwrap();
+        assert_eq!(decoded, update);
+
+        let settings = Settings::new();
+        assert_eq!(
+            settings.describe_changes(&decoded),
+            vec![
+                format!("max_updates_per_block: {} -> 2", u32::MAX),
+                format!("max_certificates_per_block: {} -> 3", u32::MAX),
+            ]
+        );
+        let settings = settings.apply(&decoded);
+        assert_eq!(settings.max_updates_per_block, 2);
+        assert_eq!(settings.max_certificates_per_block, 3);
+    }
+
+    #[test]
+    fn apply_treasury_tax() {
+        let settings = Settings::new();
+        assert_eq!(settings.treasury_tax, TaxType::zero());
+
+        let tax = TaxType::new(1, 10, Some(Value(1000))).unwrap();
+        let mut update = UpdateProposal::new();
+        update.treasury_tax = Some(tax);
+        let settings = settings.apply(&update);
+        assert_eq!(settings.treasury_tax, tax);
+
+        let settings = settings.apply(&UpdateProposal::new());
+        assert_eq!(settings.treasury_tax, tax);
+    }
+
+    #[test]
+    fn split_treasury() {
+        let mut settings = Settings::new();
+        assert_eq!(
+            settings.split_treasury(Value(1000)),
+            Ok((Value(0), Value(1000)))
+        );
+
+        settings.treasury_tax = TaxType::new(1, 1, None).unwrap();
+        assert_eq!(
+            settings.split_treasury(Value(1000)),
+            Ok((Value(1000), Value(0)))
+        );
+        assert_eq!(
+            settings.split_treasury(Value(u64::MAX)),
+            Ok((Value(u64::MAX), Value(0)))
+        );
+
+        // 10 / 3 is rounded down, the rounding error goes to the remainder
+        settings.treasury_tax = TaxType::new(1, 3, None).unwrap();
+        assert_eq!(settings.split_treasury(Value(10)), Ok((Value(3), Value(7))));
+        assert_eq!(settings.split_treasury(Value(2)), Ok((Value(0), Value(2))));
+
+        settings.treasury_tax = TaxType::new(1, 3, Some(Value(2))).unwrap();
+        assert_eq!(settings.split_treasury(Value(10)), Ok((Value(2), Value(8))));
+    }
 }
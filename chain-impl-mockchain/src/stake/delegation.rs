@@ -2,7 +2,7 @@ use crate::certificate::{Certificate, CertificateContent};
 use imhamt::{Hamt, UpdateError};
 use std::collections::hash_map::DefaultHasher;
 
-use super::role::{StakeKeyId, StakeKeyInfo, StakePoolId, StakePoolInfo};
+use super::role::{StakeKeyId, StakeKeyInfo, StakePoolId, StakePoolInfo, StakePoolInfoWithId};
 
 /// All registered Stake Node
 pub type PoolTable = Hamt<DefaultHasher, StakePoolId, StakePoolInfo>;
@@ -137,8 +137,11 @@ impl DelegationState {
             .map_or_else(|| false, |_| true)
     }
 
-    pub fn register_stake_pool(&self, owner: StakePoolInfo) -> Result<Self, DelegationError> {
-        let id = owner.to_id();
+    pub fn register_stake_pool<P>(&self, owner: P) -> Result<Self, DelegationError>
+    where
+        P: Into<StakePoolInfoWithId>,
+    {
+        let (id, owner) = owner.into().into_parts();
         let new_pools = self
             .stake_pools
             .insert(id.clone(), owner)
@@ -1,7 +1,9 @@
 mod delegation;
 mod distribution;
+mod registry;
 mod role;
 
 pub use delegation::*;
 pub use distribution::*;
+pub use registry::*;
 pub use role::*;
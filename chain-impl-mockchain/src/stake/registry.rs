@@ -0,0 +1,205 @@
+use super::role::{StakeKeyId, StakeKeyInfo, StakePoolId};
+use chain_core::mempack::{ReadBuf, ReadError, Readable};
+use chain_core::property;
+use std::collections::BTreeMap;
+
+/// Registry of the stake keys and their information.
+///
+/// Entries are kept ordered by `StakeKeyId`, so the serialized form of
+/// the registry does not depend on the order in which keys were inserted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StakeKeyRegistry(BTreeMap<StakeKeyId, StakeKeyInfo>);
+
+impl StakeKeyRegistry {
+    pub fn new() -> Self {
+        StakeKeyRegistry(BTreeMap::new())
+    }
+
+    /// Insert the information of a stake key, returning the information
+    /// previously registered for this key, if any.
+    pub fn insert(&mut self, id: StakeKeyId, info: StakeKeyInfo) -> Option<StakeKeyInfo> {
+        self.0.insert(id, info)
+    }
+
+    pub fn get(&self, id: &StakeKeyId) -> Option<&StakeKeyInfo> {
+        self.0.get(id)
+    }
+
+    pub fn remove(&mut self, id: &StakeKeyId) -> Option<StakeKeyInfo> {
+        self.0.remove(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the registered keys, in ascending `StakeKeyId` order.
+    pub fn iter(&self) -> impl Iterator<Item = (&StakeKeyId, &StakeKeyInfo)> {
+        self.0.iter()
+    }
+}
+
+impl property::Serialize for StakeKeyInfo {
+    type Error = std::io::Error;
+    fn serialize<W: std::io::Write>(&self, writer: W) -> Result<(), Self::Error> {
+        use chain_core::packer::Codec;
+
+        let mut codec = Codec::from(writer);
+        match &self.pool {
+            None => codec.put_u8(0)?,
+            Some(pool) => {
+                codec.put_u8(1)?;
+                pool.serialize(&mut codec)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Readable for StakeKeyInfo {
+    fn read<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+        let pool = match buf.get_u8()? {
+            0 => None,
+            1 => Some(StakePoolId::read(buf)?),
+            t => return Err(ReadError::UnknownTag(t as u32)),
+        };
+        Ok(StakeKeyInfo { pool })
+    }
+}
+
+impl property::Serialize for StakeKeyRegistry {
+    type Error = std::io::Error;
+    fn serialize<W: std::io::Write>(&self, writer: W) -> Result<(), Self::Error> {
+        use chain_core::packer::Codec;
+
+        if self.0.len() > u32::MAX as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "too many stake keys".to_string(),
+            ));
+        }
+
+        let mut codec = Codec::from(writer);
+        codec.put_u32(self.0.len() as u32)?;
+        for (id, info) in &self.0 {
+            id.serialize(&mut codec)?;
+            info.serialize(&mut codec)?;
+        }
+        Ok(())
+    }
+}
+
+impl Readable for StakeKeyRegistry {
+    /// Only the canonical encoding is accepted: the entries must be in
+    /// strictly ascending `StakeKeyId` order.
+    fn read<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+        let entries_nb = buf.get_u32()?;
+        let mut registry = BTreeMap::new();
+        let mut previous: Option<StakeKeyId> = None;
+        for _ in 0..entries_nb {
+            let id = StakeKeyId::read(buf)?;
+            let info = StakeKeyInfo::read(buf)?;
+            if let Some(previous) = &previous {
+                if previous >= &id {
+                    return Err(ReadError::StructureInvalid(
+                        "stake keys are not in ascending order".to_string(),
+                    ));
+                }
+            }
+            previous = Some(id.clone());
+            registry.insert(id, info);
+        }
+        Ok(StakeKeyRegistry(registry))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chain_core::property::Serialize;
+    use quickcheck::{Arbitrary, Gen, TestResult};
+
+    impl Arbitrary for StakeKeyInfo {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            StakeKeyInfo {
+                pool: Arbitrary::arbitrary(g),
+            }
+        }
+    }
+
+    impl Arbitrary for StakeKeyRegistry {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            let entries: Vec<(StakeKeyId, StakeKeyInfo)> = Arbitrary::arbitrary(g);
+            let mut registry = StakeKeyRegistry::new();
+            for (id, info) in entries {
+                registry.insert(id, info);
+            }
+            registry
+        }
+    }
+
+    quickcheck! {
+        fn stake_key_info_serialization_bijection(info: StakeKeyInfo) -> TestResult {
+            property::testing::serialization_bijection_r(info)
+        }
+
+        fn stake_key_registry_serialization_bijection(registry: StakeKeyRegistry) -> TestResult {
+            property::testing::serialization_bijection_r(registry)
+        }
+
+        fn stake_key_registry_insertion_order_independent(
+            entries: Vec<(StakeKeyId, StakeKeyInfo)>
+        ) -> bool {
+            let mut forward = StakeKeyRegistry::new();
+            for (id, info) in entries.iter().cloned() {
+                forward.insert(id, info);
+            }
+            // keep the last value of each key, as the forward insertion does
+            let mut reverse = StakeKeyRegistry::new();
+            for (id, info) in entries.iter().rev().cloned() {
+                if reverse.get(&id).is_none() {
+                    reverse.insert(id, info);
+                }
+            }
+            forward == reverse
+                && forward.serialize_as_vec().unwrap() == reverse.serialize_as_vec().unwrap()
+        }
+
+        fn stake_key_registry_insert_get_remove(
+            registry: StakeKeyRegistry,
+            id: StakeKeyId,
+            info: StakeKeyInfo
+        ) -> bool {
+            let mut registry = registry;
+            registry.insert(id.clone(), info.clone());
+            let found = registry.get(&id) == Some(&info);
+            let removed = registry.remove(&id) == Some(info);
+            found && removed && registry.get(&id).is_none()
+        }
+    }
+
+    #[test]
+    fn stake_key_registry_rejects_unsorted_entries() {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let mut ids: Vec<StakeKeyId> = (0..2).map(|_| StakeKeyId::arbitrary(&mut g)).collect();
+        ids.sort();
+        ids.reverse();
+        let info = StakeKeyInfo { pool: None };
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&2u32.to_be_bytes());
+        for id in &ids {
+            id.serialize(&mut bytes).unwrap();
+            info.serialize(&mut bytes).unwrap();
+        }
+        let mut buf = ReadBuf::from(&bytes);
+        match StakeKeyRegistry::read(&mut buf) {
+            Err(ReadError::StructureInvalid(_)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
@@ -2,7 +2,8 @@ use crate::key::{deserialize_public_key, serialize_public_key, Hash};
 use crate::leadership::genesis::GenesisPraosLeader;
 use chain_core::mempack::{ReadBuf, ReadError, Readable};
 use chain_core::property;
-use chain_crypto::{Ed25519Extended, PublicKey, SecretKey};
+use chain_crypto::bech32::{self, Bech32};
+use chain_crypto::{Blake2b256, Ed25519Extended, PublicKey, SecretKey};
 
 /// Information related to a stake key
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -13,29 +14,172 @@ pub struct StakeKeyInfo {
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct StakePoolId(Hash);
 
+impl StakePoolId {
+    /// `None` if the slice is not the size of a hash
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Blake2b256::try_from_slice(bytes)
+            .ok()
+            .map(|hash| StakePoolId(hash.into()))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StakePoolInfo {
     pub serial: u128,
-    pub owners: Vec<StakeKeyId>,
+    owners: Vec<StakeKeyId>,
     pub initial_key: GenesisPraosLeader,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StakePoolError {
+    /// the pool has no owner
+    NoOwners,
+    /// the same stake key appears more than once in the owners
+    DuplicateOwner(StakeKeyId),
+}
+
+impl std::fmt::Display for StakePoolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            StakePoolError::NoOwners => write!(f, "Stake pool has no owners"),
+            StakePoolError::DuplicateOwner(owner) => {
+                write!(f, "Stake pool has owner '{:?}' more than once", owner)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StakePoolError {}
+
 impl StakePoolInfo {
+    pub fn new(serial: u128, owners: Vec<StakeKeyId>, initial_key: GenesisPraosLeader) -> Self {
+        StakePoolInfo {
+            serial,
+            owners,
+            initial_key,
+        }
+    }
+
+    /// The owners of the pool, in the order they were given at creation.
+    pub fn owners(&self) -> impl ExactSizeIterator<Item = &StakeKeyId> {
+        self.owners.iter()
+    }
+
+    /// Check that the pool has at least one owner and that no owner is
+    /// listed twice.
+    pub fn validate(&self) -> Result<(), StakePoolError> {
+        if self.owners.is_empty() {
+            return Err(StakePoolError::NoOwners);
+        }
+        for (i, owner) in self.owners.iter().enumerate() {
+            if self.owners[..i].contains(owner) {
+                return Err(StakePoolError::DuplicateOwner(owner.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// The position of the given stake key in the pool owners, if it is
+    /// one of them.
+    pub fn owner_index(&self, key: &StakeKeyId) -> Option<usize> {
+        self.owners().position(|owner| owner == key)
+    }
+
+    pub fn is_owner(&self, key: &StakeKeyId) -> bool {
+        self.owner_index(key).is_some()
+    }
+
+    /// Compute the identifier of the pool.
+    ///
+    /// The identifier is computed over the owners as listed, so pools that
+    /// do not pass `validate` may share an identifier with a different
+    /// definition: callers should validate the pool first.
     pub fn to_id(&self) -> StakePoolId {
-        let mut v = Vec::new();
-        v.extend_from_slice(&self.serial.to_be_bytes());
-        for o in &self.owners {
-            v.extend_from_slice(o.0.as_ref())
+        let mut hasher = Hash::hasher();
+        hasher.update(&self.serial.to_be_bytes());
+        for o in self.owners() {
+            hasher.update(o.0.as_ref());
         }
-        v.extend_from_slice(self.initial_key.kes_public_key.as_ref());
-        v.extend_from_slice(self.initial_key.vrf_public_key.as_ref());
-        StakePoolId(Hash::hash_bytes(&v))
+        hasher.update(self.initial_key.kes_public_key.as_ref());
+        hasher.update(self.initial_key.vrf_public_key.as_ref());
+        StakePoolId(hasher.finalize())
+    }
+}
+
+/// A `StakePoolInfo` alongside its `StakePoolId`, computed once at
+/// construction.
+///
+/// The pool information is not exposed mutably so the cached identifier
+/// always matches `StakePoolInfo::to_id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StakePoolInfoWithId {
+    info: StakePoolInfo,
+    id: StakePoolId,
+}
+
+impl StakePoolInfoWithId {
+    pub fn new(info: StakePoolInfo) -> Self {
+        let id = info.to_id();
+        StakePoolInfoWithId { info, id }
+    }
+
+    pub fn id(&self) -> &StakePoolId {
+        &self.id
+    }
+
+    pub fn info(&self) -> &StakePoolInfo {
+        &self.info
+    }
+
+    pub fn into_parts(self) -> (StakePoolId, StakePoolInfo) {
+        (self.id, self.info)
+    }
+}
+
+impl From<StakePoolInfo> for StakePoolInfoWithId {
+    fn from(info: StakePoolInfo) -> Self {
+        StakePoolInfoWithId::new(info)
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct StakeKeyId(pub(crate) PublicKey<Ed25519Extended>);
 
+impl StakeKeyId {
+    /// `None` if the slice is not a valid public key
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        PublicKey::from_binary(bytes).ok().map(StakeKeyId)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+impl StakeKeyId {
+    /// Encode the public key as bech32 with the given human readable
+    /// part. Unlike `Bech32::to_bech32_str`, which always uses the prefix
+    /// of `PublicKey<Ed25519Extended>`, the prefix is up to the caller.
+    /// Panics if `hrp` is not a valid human readable part.
+    pub fn to_bech32(&self, hrp: &str) -> String {
+        bech32::to_bech32_from_bytes_with_hrp(hrp, self.0.as_ref())
+    }
+
+    /// Decode a stake key written by `to_bech32`, whatever its human
+    /// readable part. Fails if the string is not valid bech32 or if the
+    /// data is not an extended ed25519 public key.
+    pub fn from_bech32(s: &str) -> Result<Self, bech32::Error> {
+        let (_, bytes) = bech32::try_from_bech32_to_hrp_and_bytes(s)?;
+        PublicKey::from_binary(&bytes)
+            .map(StakeKeyId)
+            .map_err(bech32::Error::data_invalid)
+    }
+}
+
 impl From<PublicKey<Ed25519Extended>> for StakeKeyId {
     fn from(key: PublicKey<Ed25519Extended>) -> Self {
         StakeKeyId(key)
@@ -48,6 +192,20 @@ impl From<&SecretKey<Ed25519Extended>> for StakeKeyId {
     }
 }
 
+/// Stake keys are written as the bech32 of their public key, so the
+/// human readable part is the one of `PublicKey<Ed25519Extended>`.
+impl Bech32 for StakeKeyId {
+    const BECH32_HRP: &'static str = <PublicKey<Ed25519Extended> as Bech32>::BECH32_HRP;
+
+    fn try_from_bech32_str(bech32_str: &str) -> Result<Self, bech32::Error> {
+        PublicKey::try_from_bech32_str(bech32_str).map(StakeKeyId)
+    }
+
+    fn to_bech32_str(&self) -> String {
+        self.0.to_bech32_str()
+    }
+}
+
 impl property::Serialize for StakeKeyId {
     type Error = std::io::Error;
     fn serialize<W: std::io::Write>(&self, writer: W) -> Result<(), Self::Error> {
@@ -97,14 +255,20 @@ impl Readable for GenesisPraosLeader {
 impl property::Serialize for StakePoolInfo {
     type Error = std::io::Error;
     fn serialize<W: std::io::Write>(&self, writer: W) -> Result<(), Self::Error> {
-        assert!(self.owners.len() < 256);
-
         use chain_core::packer::Codec;
 
+        let owners = self.owners();
+        if owners.len() > u8::MAX as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "too many stake pool owners".to_string(),
+            ));
+        }
+
         let mut codec = Codec::from(writer);
         codec.put_u128(self.serial)?;
-        codec.put_u8(self.owners.len() as u8)?;
-        for o in &self.owners {
+        codec.put_u8(owners.len() as u8)?;
+        for o in owners {
             serialize_public_key(&o.0, &mut codec)?;
         }
         self.initial_key.serialize(&mut codec)?;
@@ -112,8 +276,8 @@ impl property::Serialize for StakePoolInfo {
     }
 }
 
-impl Readable for StakePoolInfo {
-    fn read<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+impl StakePoolInfo {
+    fn read_unlocated<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
         let serial = buf.get_u128()?;
         let owner_nb = buf.get_u8()? as usize;
         let mut owners = Vec::with_capacity(owner_nb);
@@ -123,11 +287,14 @@ impl Readable for StakePoolInfo {
         }
         let initial_key = GenesisPraosLeader::read(buf)?;
 
-        Ok(StakePoolInfo {
-            serial,
-            owners,
-            initial_key,
-        })
+        Ok(StakePoolInfo::new(serial, owners, initial_key))
+    }
+}
+
+/// Errors are located at the offset of the read that failed
+impl Readable for StakePoolInfo {
+    fn read<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+        Self::read_unlocated(buf).map_err(|e| buf.locate_error(e))
     }
 }
 
@@ -145,7 +312,43 @@ impl From<chain_crypto::Blake2b256> for StakePoolId {
 #[cfg(test)]
 mod test {
     use super::*;
-    use quickcheck::{Arbitrary, Gen};
+    use quickcheck::{Arbitrary, Gen, TestResult};
+
+    #[test]
+    fn stake_key_id_from_bech32_rejects_wrong_length() {
+        let encoded = bech32::to_bech32_from_bytes_with_hrp("stake", &[0; 31]);
+        match StakeKeyId::from_bech32(&encoded) {
+            Err(bech32::Error::DataInvalid(_)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn stake_key_id_bech32_rejects_other_hrp() {
+        let encoded = chain_crypto::Blake2b256::new(&[]).to_bech32_str();
+        match StakeKeyId::try_from_bech32_str(&encoded) {
+            Err(bech32::Error::HrpInvalid { .. }) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    quickcheck! {
+        fn stake_pool_id_bytes_roundtrip(id: StakePoolId) -> bool {
+            StakePoolId::from_bytes(id.as_bytes()) == Some(id)
+        }
+
+        fn stake_key_id_bytes_roundtrip(id: StakeKeyId) -> bool {
+            StakeKeyId::from_bytes(id.as_bytes()) == Some(id)
+        }
+    }
+
+    #[test]
+    fn ids_from_bytes_invalid_size() {
+        assert_eq!(StakePoolId::from_bytes(&[0; 31]), None);
+        assert_eq!(StakePoolId::from_bytes(&[0; 33]), None);
+        assert_eq!(StakeKeyId::from_bytes(&[0; 31]), None);
+        assert_eq!(StakeKeyId::from_bytes(&[0; 33]), None);
+    }
 
     impl Arbitrary for StakeKeyId {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
@@ -158,4 +361,91 @@ mod test {
             StakePoolId(Arbitrary::arbitrary(g))
         }
     }
+
+    impl Arbitrary for StakePoolInfo {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            use rand_core::SeedableRng;
+            let mut seed = [0; 32];
+            for byte in seed.iter_mut() {
+                *byte = Arbitrary::arbitrary(g);
+            }
+            let mut rng = rand_chacha::ChaChaRng::from_seed(seed);
+            let owners_nb = usize::arbitrary(g) % 4 + 1;
+            StakePoolInfo {
+                serial: Arbitrary::arbitrary(g),
+                owners: std::iter::repeat_with(|| StakeKeyId::from(&SecretKey::generate(&mut rng)))
+                    .take(owners_nb)
+                    .collect(),
+                initial_key: GenesisPraosLeader {
+                    vrf_public_key: SecretKey::generate(&mut rng).to_public(),
+                    kes_public_key: SecretKey::generate(&mut rng).to_public(),
+                },
+            }
+        }
+    }
+
+    quickcheck! {
+        fn stake_pool_info_serialization_bijection(info: StakePoolInfo) -> TestResult {
+            property::testing::serialization_bijection_r(info)
+        }
+
+        fn stake_pool_info_validate(info: StakePoolInfo) -> bool {
+            let owner = info.owners[0].clone();
+            let mut no_owners = info.clone();
+            no_owners.owners = vec![];
+            let mut duplicate_owner = info.clone();
+            duplicate_owner.owners.push(owner.clone());
+
+            info.validate() == Ok(())
+                && no_owners.validate() == Err(StakePoolError::NoOwners)
+                && duplicate_owner.validate() == Err(StakePoolError::DuplicateOwner(owner))
+        }
+
+        fn stake_pool_info_serialize_too_many_owners(info: StakePoolInfo) -> bool {
+            use chain_core::property::Serialize;
+            let mut info = info;
+            let owner = info.owners[0].clone();
+            info.owners = vec![owner; 256];
+            info.serialize_as_vec().is_err()
+        }
+
+        fn stake_key_id_to_bech32_roundtrip(id: StakeKeyId) -> bool {
+            let encoded = id.to_bech32("stake");
+            encoded.starts_with("stake1") && StakeKeyId::from_bech32(&encoded).ok() == Some(id)
+        }
+
+        fn stake_key_id_bech32_roundtrip(id: StakeKeyId) -> bool {
+            let encoded = id.to_bech32_str();
+            encoded.starts_with(StakeKeyId::BECH32_HRP)
+                && StakeKeyId::try_from_bech32_str(&encoded).ok() == Some(id)
+        }
+
+        fn stake_pool_info_owners_in_order(info: StakePoolInfo, owners: Vec<StakeKeyId>) -> bool {
+            let info = StakePoolInfo::new(info.serial, owners.clone(), info.initial_key);
+            info.owners().len() == owners.len() && info.owners().eq(owners.iter())
+        }
+
+        fn stake_pool_info_owner_index(info: StakePoolInfo, key: StakeKeyId) -> TestResult {
+            if info.owners.contains(&key) {
+                return TestResult::discard();
+            }
+            let last = info.owners.len() - 1;
+            let mut no_owners = info.clone();
+            no_owners.owners = vec![];
+
+            TestResult::from_bool(
+                info.owner_index(&info.owners[last]) == Some(last)
+                    && info.is_owner(&info.owners[0])
+                    && info.owner_index(&key).is_none()
+                    && !info.is_owner(&key)
+                    && no_owners.owner_index(&key).is_none()
+                    && !no_owners.is_owner(&key)
+            )
+        }
+
+        fn stake_pool_info_with_id_matches_to_id(info: StakePoolInfo) -> bool {
+            let with_id = StakePoolInfoWithId::from(info.clone());
+            with_id.id() == &info.to_id() && with_id.info() == &info
+        }
+    }
 }
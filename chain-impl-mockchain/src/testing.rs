@@ -0,0 +1,76 @@
+//! Deterministic fixtures for tests that need specific, reproducible
+//! values rather than the quickcheck generated ones.
+
+use crate::block::{Block, BlockBuilder, BlockDate, BlockId, ChainLength};
+use crate::key::{Hash, SpendingSecretKey};
+use crate::message::Message;
+use crate::transaction::{
+    AuthenticatedTransaction, Input, NoExtra, Output, Transaction, UtxoPointer, Witness,
+};
+use crate::value::Value;
+use chain_addr::{Address, Discrimination, Kind};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+
+impl Block {
+    /// Build a BFT block with `num_messages` transactions, all generated
+    /// from the given seed: the same seed always gives the same block.
+    /// The content hash and size in the header match the contents.
+    pub fn testing_fixture(seed: u64, num_messages: usize) -> Block {
+        let mut rng = ChaChaRng::seed_from_u64(seed);
+
+        let leader = SpendingSecretKey::generate(&mut rng);
+        let parent: BlockId = Hash::from_bytes(rng.gen());
+        let date = BlockDate {
+            epoch: rng.gen_range(0, 1000),
+            slot_id: rng.gen_range(0, 1000),
+        };
+        let chain_length = ChainLength(rng.gen_range(1, 1000));
+
+        let mut builder = BlockBuilder::new();
+        builder.parent(parent).date(date).chain_length(chain_length);
+        for _ in 0..num_messages {
+            builder.message(fixture_transaction(&mut rng));
+        }
+        builder.make_bft_block(&leader)
+    }
+}
+
+/// a transaction spending one UTXO to one single address, with a valid
+/// witness
+fn fixture_transaction(rng: &mut ChaChaRng) -> Message {
+    let secret_key = SpendingSecretKey::generate(&mut *rng);
+    let value = Value(rng.gen_range(1, 1_000_000));
+    let utxo = UtxoPointer::new(Hash::from_bytes(rng.gen()), rng.gen(), value);
+    let address = Address(Discrimination::Test, Kind::Single(secret_key.to_public()));
+
+    let transaction = Transaction {
+        inputs: vec![Input::from_utxo(utxo)],
+        outputs: vec![Output { address, value }],
+        extra: NoExtra,
+    };
+    let witness = Witness::new_utxo(&transaction.hash(), &secret_key);
+    Message::Transaction(AuthenticatedTransaction {
+        transaction,
+        witnesses: vec![witness],
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn block_testing_fixture_deterministic() {
+        let block = Block::testing_fixture(42, 3);
+        assert!(block.is_consistent());
+        assert_eq!(block.message_counts().transaction, 3);
+
+        let same = Block::testing_fixture(42, 3);
+        assert_eq!(same, block);
+        assert_eq!(same.contents, block.contents);
+
+        assert_ne!(Block::testing_fixture(43, 3), block);
+        assert_ne!(Block::testing_fixture(42, 2), block);
+    }
+}
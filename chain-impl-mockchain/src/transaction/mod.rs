@@ -3,6 +3,7 @@ mod transfer;
 mod utxo;
 mod witness;
 
+use crate::account;
 use chain_addr::Address;
 use chain_core::mempack::{read_vec, ReadBuf, ReadError, Readable};
 use chain_core::property;
@@ -21,6 +22,112 @@ pub struct AuthenticatedTransaction<OutAddress, Extra> {
     pub witnesses: Vec<Witness>,
 }
 
+/// An `AuthenticatedTransaction` along with its `TransactionId`.
+///
+/// The id is computed once when the wrapper is created, so it can be
+/// used repeatedly (to verify witnesses, to index the transaction...)
+/// without hashing the transaction again. The transaction cannot be
+/// modified in place: rebuilding it means creating a new wrapper.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdentifiedTransaction<Extra> {
+    transaction: AuthenticatedTransaction<Address, Extra>,
+    id: TransactionId,
+}
+
+impl<Extra: property::Serialize> IdentifiedTransaction<Extra> {
+    pub fn new(transaction: AuthenticatedTransaction<Address, Extra>) -> Self {
+        let id = transaction.transaction.hash();
+        IdentifiedTransaction { transaction, id }
+    }
+}
+
+impl<Extra> IdentifiedTransaction<Extra> {
+    pub fn id(&self) -> TransactionId {
+        self.id
+    }
+
+    pub fn transaction(&self) -> &AuthenticatedTransaction<Address, Extra> {
+        &self.transaction
+    }
+
+    pub fn into_inner(self) -> AuthenticatedTransaction<Address, Extra> {
+        self.transaction
+    }
+}
+
+impl<Extra: property::Serialize> From<AuthenticatedTransaction<Address, Extra>>
+    for IdentifiedTransaction<Extra>
+{
+    fn from(transaction: AuthenticatedTransaction<Address, Extra>) -> Self {
+        IdentifiedTransaction::new(transaction)
+    }
+}
+
+impl<OutAddress, Extra> AuthenticatedTransaction<OutAddress, Extra> {
+    /// Check that no witness appears more than once in the transaction,
+    /// failing on the first duplicate found.
+    ///
+    /// The spending counter of an account witness is signed but not part
+    /// of the witness, so reused counters cannot be told from the witnesses
+    /// alone: use `check_spending_counter_uniqueness` for them.
+    pub fn check_witness_uniqueness(&self) -> Result<(), WitnessError> {
+        for (index, witness) in self.witnesses.iter().enumerate() {
+            if let Some(duplicate_of) = self.witnesses[..index]
+                .iter()
+                .position(|previous| previous == witness)
+            {
+                return Err(WitnessError::DuplicateWitness {
+                    index,
+                    duplicate_of,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that no account input is witnessed twice with the same
+    /// spending counter, given the counter each input's witness was made
+    /// for, `None` for the UTXO inputs. Fails on the first reused counter
+    /// found, or if there is not exactly one counter per input.
+    ///
+    /// Different accounts may use the same counter.
+    pub fn check_spending_counter_uniqueness(
+        &self,
+        counters: &[Option<account::SpendingCounter>],
+    ) -> Result<(), WitnessError> {
+        let inputs = &self.transaction.inputs;
+        if inputs.len() != counters.len() {
+            return Err(WitnessError::CountersMismatch {
+                inputs: inputs.len(),
+                counters: counters.len(),
+            });
+        }
+        let spent: Vec<_> = inputs
+            .iter()
+            .zip(counters.iter())
+            .map(|(input, counter)| match (input.to_enum(), counter) {
+                (InputEnum::AccountInput(account, _), Some(counter)) => Some((account, *counter)),
+                _ => None,
+            })
+            .collect();
+        for (index, current) in spent.iter().enumerate() {
+            if current.is_none() {
+                continue;
+            }
+            if let Some(duplicate_of) = spent[..index]
+                .iter()
+                .position(|previous| previous == current)
+            {
+                return Err(WitnessError::DuplicateSpendingCounter {
+                    index,
+                    duplicate_of,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
 impl<Extra: property::Serialize> property::Serialize for AuthenticatedTransaction<Address, Extra> {
     type Error = Extra::Error;
 
@@ -51,19 +158,274 @@ impl<Extra: Readable> Readable for AuthenticatedTransaction<Address, Extra> {
     }
 }
 
+/// Estimate the serialized size of an `AuthenticatedTransaction` without
+/// extra, once it has been signed with the given witnesses.
+///
+/// Witness sizes depend on their type, hence the count per type. Outputs
+/// are counted at the size of the largest address kind (group addresses),
+/// so the estimate is exact for group outputs and an upper bound otherwise.
+pub fn estimate_size(witnesses: &WitnessCounts, num_outputs: usize) -> usize {
+    const HEADER_SIZE: usize = 2;
+    const OUTPUT_SIZE: usize = chain_addr::ADDR_SIZE_GROUP + 8;
+
+    HEADER_SIZE
+        + witnesses.total() * transfer::INPUT_SIZE
+        + num_outputs * OUTPUT_SIZE
+        + witnesses.serialized_size()
+}
+
 #[cfg(test)]
 mod test {
+    use super::witness::test::TransactionSigningKey;
     use super::*;
-    use crate::value::Value;
-    use quickcheck::{Arbitrary, Gen, TestResult};
+    use crate::fee::LinearFee;
+    use crate::key::{Hash, SpendingPublicKey};
+    use crate::value::{Value, ValueError};
+    use quickcheck::{Arbitrary, Gen, StdThreadGen, TestResult};
 
     quickcheck! {
+        fn estimate_size_upper_bound(transaction: AuthenticatedTransaction<Address, NoExtra>) -> bool {
+            use chain_core::property::Serialize as _;
+            let actual = transaction.serialize_as_vec().unwrap().len();
+            let counts: WitnessCounts = transaction.witnesses.iter().collect();
+            estimate_size(&counts, transaction.transaction.outputs.len()) >= actual
+        }
+
+        fn estimate_size_exact_for_group_outputs(
+            transaction: AuthenticatedTransaction<Address, NoExtra>,
+            spending: SpendingPublicKey,
+            group: SpendingPublicKey
+        ) -> bool {
+            use chain_addr::{Discrimination, Kind};
+            use chain_core::property::Serialize as _;
+            let mut transaction = transaction;
+            for output in transaction.transaction.outputs.iter_mut() {
+                output.address = Address(
+                    Discrimination::Production,
+                    Kind::Group(spending.clone(), group.clone()),
+                );
+            }
+            let actual = transaction.serialize_as_vec().unwrap().len();
+            let counts: WitnessCounts = transaction.witnesses.iter().collect();
+            estimate_size(&counts, transaction.transaction.outputs.len()) == actual
+        }
+
         fn transaction_encode_decode(transaction: Transaction<Address, NoExtra>) -> TestResult {
             chain_core::property::testing::serialization_bijection_r(transaction)
         }
         fn signed_transaction_encode_decode(transaction: AuthenticatedTransaction<Address, NoExtra>) -> TestResult {
             chain_core::property::testing::serialization_bijection_r(transaction)
         }
+
+        fn identified_transaction_id_matches(transaction: AuthenticatedTransaction<Address, NoExtra>) -> bool {
+            use chain_core::property::Serialize as _;
+            // skip the header holding the number of inputs and outputs
+            let bytes = transaction.transaction.serialize_as_vec().unwrap();
+            let expected = Hash::hash_bytes(&bytes[2..]);
+            let identified = IdentifiedTransaction::new(transaction.clone());
+            identified.id() == expected
+                && identified.id() == identified.id()
+                && identified.into_inner() == transaction
+        }
+    }
+
+    fn transaction_with_values(inputs: &[u64], outputs: &[u64]) -> Transaction<Address, NoExtra> {
+        let mut g = StdThreadGen::new(10);
+        Transaction {
+            inputs: inputs
+                .iter()
+                .enumerate()
+                .map(|(index, value)| {
+                    Input::from_utxo(UtxoPointer::new(
+                        Hash::hash_bytes(b"input"),
+                        index as u8,
+                        Value(*value),
+                    ))
+                })
+                .collect(),
+            outputs: outputs
+                .iter()
+                .map(|value| Output {
+                    address: Address::arbitrary(&mut g),
+                    value: Value(*value),
+                })
+                .collect(),
+            extra: NoExtra,
+        }
+    }
+
+    #[test]
+    fn check_witness_uniqueness() {
+        use crate::account::SpendingCounter;
+
+        let mut g = StdThreadGen::new(10);
+        let sk1 = TransactionSigningKey::arbitrary(&mut g).0;
+        let sk2 = TransactionSigningKey::arbitrary(&mut g).0;
+        let transaction = transaction_with_values(&[10, 20, 30], &[50]);
+        let id = transaction.hash();
+        let counter = SpendingCounter::from(3);
+        let signed = |witnesses| AuthenticatedTransaction {
+            transaction: transaction.clone(),
+            witnesses,
+        };
+
+        let clean = signed(vec![
+            Witness::new_utxo(&id, &sk1),
+            Witness::new_utxo(&id, &sk2),
+            Witness::new_account(&id, &counter, &sk1),
+        ]);
+        assert_eq!(clean.check_witness_uniqueness(), Ok(()));
+
+        let duplicate = signed(vec![
+            Witness::new_utxo(&id, &sk1),
+            Witness::new_utxo(&id, &sk2),
+            Witness::new_utxo(&id, &sk1),
+        ]);
+        assert_eq!(
+            duplicate.check_witness_uniqueness(),
+            Err(WitnessError::DuplicateWitness {
+                index: 2,
+                duplicate_of: 0
+            })
+        );
+
+        let reused_counter = signed(vec![
+            Witness::new_utxo(&id, &sk2),
+            Witness::new_account(&id, &counter, &sk1),
+            Witness::new_account(&id, &counter, &sk1),
+        ]);
+        assert_eq!(
+            reused_counter.check_witness_uniqueness(),
+            Err(WitnessError::DuplicateWitness {
+                index: 2,
+                duplicate_of: 1
+            })
+        );
+    }
+
+    #[test]
+    fn check_spending_counter_uniqueness() {
+        use crate::account::SpendingCounter;
+
+        let mut g = StdThreadGen::new(10);
+        let sk1 = TransactionSigningKey::arbitrary(&mut g).0;
+        let sk2 = TransactionSigningKey::arbitrary(&mut g).0;
+        let account1 = account::Identifier::from(sk1.to_public());
+        let account2 = account::Identifier::from(sk2.to_public());
+        let mut transaction = transaction_with_values(&[10], &[50]);
+        transaction.inputs.extend(vec![
+            Input::from_account(account1.clone(), Value(20)),
+            Input::from_account(account2.clone(), Value(20)),
+            Input::from_account(account1.clone(), Value(20)),
+        ]);
+        let id = transaction.hash();
+        let signed = |counters: &[u32]| AuthenticatedTransaction {
+            transaction: transaction.clone(),
+            witnesses: vec![
+                Witness::new_utxo(&id, &sk1),
+                Witness::new_account(&id, &SpendingCounter::from(counters[0]), &sk1),
+                Witness::new_account(&id, &SpendingCounter::from(counters[1]), &sk2),
+                Witness::new_account(&id, &SpendingCounter::from(counters[2]), &sk1),
+            ],
+        };
+        let counters_of = |counters: &[u32]| {
+            std::iter::once(None)
+                .chain(counters.iter().map(|c| Some(SpendingCounter::from(*c))))
+                .collect::<Vec<_>>()
+        };
+
+        // both accounts use the same counter, which is fine
+        let clean = signed(&[3, 3, 4]);
+        assert_eq!(clean.check_witness_uniqueness(), Ok(()));
+        assert_eq!(
+            clean.check_spending_counter_uniqueness(&counters_of(&[3, 3, 4])),
+            Ok(())
+        );
+
+        // the first account spends twice with the same counter: the
+        // witnesses are the same, and so are the counters
+        let reused = signed(&[3, 3, 3]);
+        assert_eq!(
+            reused.check_spending_counter_uniqueness(&counters_of(&[3, 3, 3])),
+            Err(WitnessError::DuplicateSpendingCounter {
+                index: 3,
+                duplicate_of: 1
+            })
+        );
+
+        assert_eq!(
+            clean.check_spending_counter_uniqueness(&counters_of(&[3, 3])),
+            Err(WitnessError::CountersMismatch {
+                inputs: 4,
+                counters: 3
+            })
+        );
+    }
+
+    #[test]
+    fn estimate_size_depends_on_witness_type() {
+        let utxo = WitnessCounts {
+            utxo: 1,
+            ..WitnessCounts::default()
+        };
+        let account = WitnessCounts {
+            account: 1,
+            ..WitnessCounts::default()
+        };
+        let old_utxo = WitnessCounts {
+            old_utxo: 1,
+            ..WitnessCounts::default()
+        };
+        assert_eq!(estimate_size(&utxo, 1), estimate_size(&account, 1));
+        assert!(estimate_size(&old_utxo, 1) > estimate_size(&utxo, 1));
+        assert_eq!(estimate_size(&WitnessCounts::default(), 0), 2);
+    }
+
+    #[test]
+    fn check_balance_balanced() {
+        // fee: 1 * (2 inputs + 1 output) + 10
+        let fee = LinearFee::new(10, 1, 0);
+        let tx = transaction_with_values(&[100, 13], &[100]);
+        assert_eq!(tx.check_balance(&fee), Ok(()));
+    }
+
+    #[test]
+    fn check_balance_not_balanced() {
+        let fee = LinearFee::new(10, 1, 0);
+        let tx = transaction_with_values(&[100, 20], &[100]);
+        assert_eq!(
+            tx.check_balance(&fee),
+            Err(BalanceError::NotBalanced {
+                inputs: Value(120),
+                outputs: Value(100),
+                fee: Value(13),
+            })
+        );
+    }
+
+    #[test]
+    fn check_balance_overflow() {
+        let fee = LinearFee::new(0, 0, 0);
+        let tx = transaction_with_values(&[u64::MAX, 1], &[10]);
+        assert_eq!(tx.check_balance(&fee), Err(BalanceError::ValueOverflow));
+        let tx = transaction_with_values(&[10], &[u64::MAX, 1]);
+        assert_eq!(tx.check_balance(&fee), Err(BalanceError::ValueOverflow));
+        let fee = LinearFee::new(1, 0, 0);
+        let tx = transaction_with_values(&[10], &[u64::MAX]);
+        assert_eq!(tx.check_balance(&fee), Err(BalanceError::ValueOverflow));
+    }
+
+    #[test]
+    fn checked_totals_overflow() {
+        let tx = transaction_with_values(&[1, 2], &[u64::MAX, 1]);
+        assert_eq!(tx.checked_total_input(), Ok(Value(3)));
+        assert_eq!(tx.checked_total_output(), Err(ValueError::Overflow));
+        match tx.balance(Value(0)) {
+            Err(ValueError::Overflow) => {}
+            _ => panic!("expected an overflow"),
+        }
+        let tx = transaction_with_values(&[u64::MAX, u64::MAX], &[1]);
+        assert_eq!(tx.checked_total_input(), Err(ValueError::Overflow));
     }
 
     impl Arbitrary for Value {
@@ -1,4 +1,5 @@
 use super::transfer::*;
+use crate::fee::{CertificateCount, FeeAlgorithm, LinearFee};
 use crate::key::Hash;
 use crate::value::{Value, ValueError};
 use chain_addr::Address;
@@ -49,6 +50,61 @@ pub enum Balance {
     Zero,
 }
 
+/// Error returned when checking a transaction is balanced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BalanceError {
+    /// the inputs do not match the outputs plus the fee
+    NotBalanced {
+        inputs: Value,
+        outputs: Value,
+        fee: Value,
+    },
+    /// the sum of the inputs, the outputs or the fee overflowed
+    ValueOverflow,
+}
+
+impl std::fmt::Display for BalanceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BalanceError::NotBalanced {
+                inputs,
+                outputs,
+                fee,
+            } => write!(
+                f,
+                "Transaction is not balanced: inputs {} but outputs {} and fee {}",
+                inputs.0, outputs.0, fee.0
+            ),
+            BalanceError::ValueOverflow => write!(f, "Transaction value overflowed"),
+        }
+    }
+}
+impl std::error::Error for BalanceError {}
+
+impl<Extra: CertificateCount> Transaction<Address, Extra> {
+    /// Check the inputs are exactly the outputs plus the fee computed
+    /// with the given fee algorithm.
+    pub fn check_balance(&self, fee: &LinearFee) -> Result<(), BalanceError> {
+        let inputs = self
+            .checked_total_input()
+            .map_err(|_| BalanceError::ValueOverflow)?;
+        let outputs = self
+            .checked_total_output()
+            .map_err(|_| BalanceError::ValueOverflow)?;
+        let fee = fee.calculate_for(self).ok_or(BalanceError::ValueOverflow)?;
+        let expected = (outputs + fee).map_err(|_| BalanceError::ValueOverflow)?;
+        if inputs == expected {
+            Ok(())
+        } else {
+            Err(BalanceError::NotBalanced {
+                inputs,
+                outputs,
+                fee,
+            })
+        }
+    }
+}
+
 impl<Extra: Readable> Transaction<Address, Extra> {
     fn read_body<'a>(
         buf: &mut ReadBuf<'a>,
@@ -198,17 +254,31 @@ impl<A, Extra> Transaction<A, Extra> {
         }
     }
 
+    #[deprecated(note = "use `checked_total_input`")]
     pub fn total_input(&self) -> Result<Value, ValueError> {
-        Value::sum(self.inputs.iter().map(|input| input.value))
+        self.checked_total_input()
     }
 
+    #[deprecated(note = "use `checked_total_output`")]
     pub fn total_output(&self) -> Result<Value, ValueError> {
+        self.checked_total_output()
+    }
+
+    /// sum of the inputs' values, failing instead of wrapping around
+    /// on overflow
+    pub fn checked_total_input(&self) -> Result<Value, ValueError> {
+        Value::sum(self.inputs.iter().map(|input| input.value))
+    }
+
+    /// sum of the outputs' values, failing instead of wrapping around
+    /// on overflow
+    pub fn checked_total_output(&self) -> Result<Value, ValueError> {
         Value::sum(self.outputs.iter().map(|output| output.value))
     }
 
     pub fn balance(&self, fee: Value) -> Result<Balance, ValueError> {
-        let inputs = self.total_input()?;
-        let outputs = self.total_output()?;
+        let inputs = self.checked_total_input()?;
+        let outputs = self.checked_total_output()?;
         let z = (outputs + fee)?;
         if inputs > z {
             Ok(Balance::Positive((inputs - z)?))
@@ -8,6 +8,10 @@ use chain_crypto::PublicKey;
 
 const INPUT_PTR_SIZE: usize = 32;
 
+/// Size of a serialized `Input`: the index or account marker, the value
+/// and the input pointer.
+pub(super) const INPUT_SIZE: usize = 1 + 8 + INPUT_PTR_SIZE;
+
 /// Generalized input which have a specific input value, and
 /// either contains an account reference or a TransactionId+index
 ///
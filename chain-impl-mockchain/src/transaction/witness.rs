@@ -1,12 +1,17 @@
 use super::transaction::*;
+use super::transfer::Input;
 use crate::account;
 use crate::key::{
-    deserialize_public_key, deserialize_signature, serialize_public_key, serialize_signature,
-    AccountSecretKey, AccountSignature, SpendingPublicKey, SpendingSecretKey, SpendingSignature,
+    deserialize_public_key_with_label, deserialize_signature_with_label, serialize_public_key,
+    serialize_signature, AccountPublicKey, AccountSecretKey, AccountSignature, SpendingPublicKey,
+    SpendingSecretKey, SpendingSignature,
 };
-use chain_core::mempack::{ReadBuf, ReadError, Readable};
+use chain_core::mempack::{read_from_raw, ReadBuf, ReadError, Readable};
 use chain_core::property;
-use chain_crypto::{Ed25519Bip32, PublicKey, Signature, Verification};
+use chain_crypto::{
+    AsymmetricKey, Ed25519Bip32, Ed25519Extended, PublicKey, SecretKey, Signature, Verification,
+    VerificationAlgorithm,
+};
 
 /// Structure that proofs that certain user agrees with
 /// some data. This structure is used to sign `Transaction`
@@ -24,13 +29,17 @@ pub enum Witness {
     ),
 }
 
+/// Signatures and public keys are compared in constant time, so that
+/// checking a witness against an expected one does not leak how many
+/// leading bytes match.
 impl PartialEq for Witness {
     fn eq(&self, rhs: &Self) -> bool {
+        use cryptoxide::util::fixed_time_eq;
         match (self, rhs) {
-            (Witness::Utxo(s1), Witness::Utxo(s2)) => s1.as_ref() == s2.as_ref(),
-            (Witness::Account(s1), Witness::Account(s2)) => s1.as_ref() == s2.as_ref(),
+            (Witness::Utxo(s1), Witness::Utxo(s2)) => fixed_time_eq(s1.as_ref(), s2.as_ref()),
+            (Witness::Account(s1), Witness::Account(s2)) => fixed_time_eq(s1.as_ref(), s2.as_ref()),
             (Witness::OldUtxo(p1, s1), Witness::OldUtxo(p2, s2)) => {
-                s1.as_ref() == s2.as_ref() && p1 == p2
+                fixed_time_eq(s1.as_ref(), s2.as_ref()) & p1.constant_time_eq(p2)
             }
             (_, _) => false,
         }
@@ -38,6 +47,18 @@ impl PartialEq for Witness {
 }
 impl Eq for Witness {}
 
+/// The data signed by an account witness.
+///
+/// The bytes are laid out as:
+///
+/// ```text
+/// +-----+----------------------+-------------------------+
+/// | tag | transaction id       | spending counter        |
+/// | 1   | 32 bytes             | 4 bytes, little endian  |
+/// +-----+----------------------+-------------------------+
+/// ```
+///
+/// where the tag is the account witness tag.
 pub struct TransactionIdSpendingCounter(Vec<u8>);
 
 impl TransactionIdSpendingCounter {
@@ -45,12 +66,17 @@ impl TransactionIdSpendingCounter {
         transaction_id: &TransactionId,
         spending_counter: &account::SpendingCounter,
     ) -> Self {
-        let mut v = Vec::new();
-        v[0] = WITNESS_TAG_ACCOUNT;
+        let mut v = Vec::with_capacity(1 + 32 + 4);
+        v.push(WITNESS_TAG_ACCOUNT);
         v.extend_from_slice(transaction_id.as_ref());
         v.extend_from_slice(&spending_counter.to_bytes());
         TransactionIdSpendingCounter(v)
     }
+
+    /// the signed bytes, see the type documentation for the layout
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
 }
 
 impl AsRef<[u8]> for TransactionIdSpendingCounter {
@@ -59,12 +85,72 @@ impl AsRef<[u8]> for TransactionIdSpendingCounter {
     }
 }
 
+/// Number of witnesses of each type a transaction will carry, used to
+/// estimate its size before it is signed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WitnessCounts {
+    pub old_utxo: usize,
+    pub utxo: usize,
+    pub account: usize,
+}
+
+impl WitnessCounts {
+    /// Size of a serialized `Witness::OldUtxo`: tag, public key and signature.
+    pub const OLD_UTXO_WITNESS_SIZE: usize = 1
+        + <Ed25519Bip32 as AsymmetricKey>::PUBLIC_KEY_SIZE
+        + <Ed25519Bip32 as VerificationAlgorithm>::SIGNATURE_SIZE;
+    /// Size of a serialized `Witness::Utxo`: tag and signature.
+    pub const UTXO_WITNESS_SIZE: usize =
+        1 + <Ed25519Extended as VerificationAlgorithm>::SIGNATURE_SIZE;
+    /// Size of a serialized `Witness::Account`: tag and signature.
+    pub const ACCOUNT_WITNESS_SIZE: usize =
+        1 + <Ed25519Extended as VerificationAlgorithm>::SIGNATURE_SIZE;
+
+    /// Total number of witnesses, which is also the number of inputs.
+    pub fn total(&self) -> usize {
+        self.old_utxo + self.utxo + self.account
+    }
+
+    /// Size of the serialized witnesses.
+    pub fn serialized_size(&self) -> usize {
+        self.old_utxo * Self::OLD_UTXO_WITNESS_SIZE
+            + self.utxo * Self::UTXO_WITNESS_SIZE
+            + self.account * Self::ACCOUNT_WITNESS_SIZE
+    }
+}
+
+impl<'a> std::iter::FromIterator<&'a Witness> for WitnessCounts {
+    fn from_iter<I: IntoIterator<Item = &'a Witness>>(iter: I) -> Self {
+        let mut counts = WitnessCounts::default();
+        for witness in iter {
+            match witness {
+                Witness::OldUtxo(_, _) => counts.old_utxo += 1,
+                Witness::Utxo(_) => counts.utxo += 1,
+                Witness::Account(_) => counts.account += 1,
+            }
+        }
+        counts
+    }
+}
+
 impl Witness {
     /// Creates new `Witness` value.
     pub fn new_utxo(transaction_id: &TransactionId, secret_key: &SpendingSecretKey) -> Self {
         Witness::Utxo(SpendingSignature::generate(secret_key, transaction_id))
     }
 
+    /// Same as `new_utxo`, also returning the public key the witness
+    /// verifies against, so the two cannot come from different keys.
+    pub fn new_utxo_with_public(
+        transaction_id: &TransactionId,
+        secret_key: &SpendingSecretKey,
+    ) -> (Self, SpendingPublicKey) {
+        (
+            Witness::new_utxo(transaction_id, secret_key),
+            secret_key.to_public(),
+        )
+    }
+
     pub fn new_account(
         transaction_id: &TransactionId,
         spending_counter: &account::SpendingCounter,
@@ -76,18 +162,241 @@ impl Witness {
         ))
     }
 
+    /// Creates new legacy `Witness` value, which carries the public key
+    /// of the signing key alongside the signature.
+    pub fn new_old_utxo(
+        transaction_id: &TransactionId,
+        secret_key: &SecretKey<Ed25519Bip32>,
+    ) -> Self {
+        Witness::OldUtxo(
+            secret_key.to_public(),
+            Signature::generate(secret_key, transaction_id),
+        )
+    }
+
+    /// Number of bytes written by `property::Serialize`, without
+    /// serializing the witness.
+    pub fn serialized_size(&self) -> usize {
+        match self {
+            Witness::OldUtxo(_, _) => WitnessCounts::OLD_UTXO_WITNESS_SIZE,
+            Witness::Utxo(_) => WitnessCounts::UTXO_WITNESS_SIZE,
+            Witness::Account(_) => WitnessCounts::ACCOUNT_WITNESS_SIZE,
+        }
+    }
+
     /// Verify the given `TransactionId` using the witness.
+    ///
+    /// Legacy witnesses are not signed with a `SpendingPublicKey` and
+    /// always fail, use `verify_old_utxo` for them.
     pub fn verify_utxo(
         &self,
         public_key: &SpendingPublicKey,
         transaction_id: &TransactionId,
     ) -> Verification {
         match self {
-            Witness::OldUtxo(_xpub, _signature) => unimplemented!(),
+            Witness::OldUtxo(_, _) => Verification::Failed,
             Witness::Utxo(signature) => signature.verify(public_key, transaction_id),
             Witness::Account(_) => Verification::Failed,
         }
     }
+
+    /// Verify an account witness for the given `TransactionId`, against
+    /// the account's public key and the spending counter the account is
+    /// expected to be at.
+    ///
+    /// The witness signs the spending counter along with the transaction
+    /// id, so a witness made for any other counter (e.g. a replayed
+    /// witness with a stale counter) fails.
+    pub fn verify_account_with_state(
+        &self,
+        public_key: &AccountPublicKey,
+        transaction_id: &TransactionId,
+        expected_counter: &account::SpendingCounter,
+    ) -> Verification {
+        match self {
+            Witness::Account(signature) => signature.verify(
+                public_key,
+                &TransactionIdSpendingCounter::new(transaction_id, expected_counter),
+            ),
+            Witness::OldUtxo(_, _) => Verification::Failed,
+            Witness::Utxo(_) => Verification::Failed,
+        }
+    }
+
+    /// Verify the given `TransactionId` using a legacy witness, against
+    /// the public key it carries. The caller is responsible for checking
+    /// this public key matches the spent address.
+    pub fn verify_old_utxo(&self, transaction_id: &TransactionId) -> Verification {
+        match self {
+            Witness::OldUtxo(xpub, signature) => signature.verify(xpub, transaction_id),
+            Witness::Utxo(_) => Verification::Failed,
+            Witness::Account(_) => Verification::Failed,
+        }
+    }
+
+    /// Same as `verify_old_utxo`, also checking the witness carries the
+    /// expected public key. A legacy public key is the key the signature
+    /// is made with followed by a chain code, only the former is compared
+    /// with `public_key`, in constant time.
+    pub fn verify_old_utxo_with_key(
+        &self,
+        public_key: &SpendingPublicKey,
+        transaction_id: &TransactionId,
+    ) -> Verification {
+        use cryptoxide::util::fixed_time_eq;
+        match self {
+            Witness::OldUtxo(xpub, _) => {
+                let key = &xpub.as_ref()[..<Ed25519Extended as AsymmetricKey>::PUBLIC_KEY_SIZE];
+                if fixed_time_eq(key, public_key.as_ref()) {
+                    self.verify_old_utxo(transaction_id)
+                } else {
+                    Verification::Failed
+                }
+            }
+            Witness::Utxo(_) => Verification::Failed,
+            Witness::Account(_) => Verification::Failed,
+        }
+    }
+}
+
+/// Error returned when checking the witnesses of a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WitnessError {
+    /// the witness at `index` is the same as the one at `duplicate_of`
+    DuplicateWitness { index: usize, duplicate_of: usize },
+    /// the input at `index` spends from the same account with the same
+    /// spending counter as the one at `duplicate_of`
+    DuplicateSpendingCounter { index: usize, duplicate_of: usize },
+    /// the number of spending counters does not match the number of inputs
+    CountersMismatch { inputs: usize, counters: usize },
+}
+
+impl std::fmt::Display for WitnessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WitnessError::DuplicateWitness {
+                index,
+                duplicate_of,
+            } => write!(
+                f,
+                "Witness {} is a duplicate of witness {}",
+                index, duplicate_of
+            ),
+            WitnessError::DuplicateSpendingCounter {
+                index,
+                duplicate_of,
+            } => write!(
+                f,
+                "Input {} reuses the spending counter of input {}",
+                index, duplicate_of
+            ),
+            WitnessError::CountersMismatch { inputs, counters } => write!(
+                f,
+                "Expected {} spending counters, one per input, but got {}",
+                inputs, counters
+            ),
+        }
+    }
+}
+impl std::error::Error for WitnessError {}
+
+/// Verify every witness of a transaction against the public key of the
+/// input it spends, returning `Success` only if they all verify.
+///
+/// Each input comes with the spending counter its account is expected
+/// to be at, `None` for UTXO inputs:
+///
+/// * `Utxo` witnesses are checked against the given public key;
+/// * `OldUtxo` witnesses are checked against the given public key, as
+///   with `Witness::verify_old_utxo_with_key`;
+/// * `Account` witnesses are checked against the given public key and
+///   spending counter, as with `Witness::verify_account_with_state`.
+///   They fail if no spending counter is given.
+pub fn verify_witnesses(
+    transaction_id: &TransactionId,
+    inputs: &[(SpendingPublicKey, Option<account::SpendingCounter>, Witness)],
+) -> Verification {
+    let all_verified = inputs.iter().all(|(public_key, counter, witness)| {
+        verify_spending(witness, public_key, counter.as_ref(), transaction_id)
+            == Verification::Success
+    });
+    if all_verified {
+        Verification::Success
+    } else {
+        Verification::Failed
+    }
+}
+
+/// Verify a single witness the way `verify_witnesses` does
+fn verify_spending(
+    witness: &Witness,
+    public_key: &SpendingPublicKey,
+    counter: Option<&account::SpendingCounter>,
+    transaction_id: &TransactionId,
+) -> Verification {
+    match (witness, counter) {
+        (Witness::Utxo(_), _) => witness.verify_utxo(public_key, transaction_id),
+        (Witness::OldUtxo(_, _), _) => witness.verify_old_utxo_with_key(public_key, transaction_id),
+        (Witness::Account(_), Some(counter)) => {
+            witness.verify_account_with_state(public_key, transaction_id, counter)
+        }
+        (Witness::Account(_), None) => Verification::Failed,
+    }
+}
+
+/// An input along with the public key it is spent with and the witness
+/// authorizing it, which can only be built once the witness verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthenticatedInput {
+    input: Input,
+    public_key: SpendingPublicKey,
+    spending_counter: Option<account::SpendingCounter>,
+    witness: Witness,
+}
+
+impl AuthenticatedInput {
+    /// Pair the input with its public key, spending counter and witness
+    /// if the witness verifies the given transaction, with the same rules
+    /// as `verify_witnesses`: legacy witnesses must carry `public_key`,
+    /// and account witnesses fail if no spending counter is given.
+    pub fn verify(
+        input: Input,
+        public_key: SpendingPublicKey,
+        spending_counter: Option<account::SpendingCounter>,
+        witness: Witness,
+        transaction_id: &TransactionId,
+    ) -> Result<Self, Verification> {
+        match verify_spending(
+            &witness,
+            &public_key,
+            spending_counter.as_ref(),
+            transaction_id,
+        ) {
+            Verification::Success => Ok(AuthenticatedInput {
+                input,
+                public_key,
+                spending_counter,
+                witness,
+            }),
+            Verification::Failed => Err(Verification::Failed),
+        }
+    }
+
+    pub fn input(&self) -> &Input {
+        &self.input
+    }
+
+    pub fn public_key(&self) -> &SpendingPublicKey {
+        &self.public_key
+    }
+
+    pub fn spending_counter(&self) -> Option<&account::SpendingCounter> {
+        self.spending_counter.as_ref()
+    }
+
+    pub fn witness(&self) -> &Witness {
+        &self.witness
+    }
 }
 
 const WITNESS_TAG_OLDUTXO: u8 = 0u8;
@@ -120,25 +429,173 @@ impl property::Serialize for Witness {
     }
 }
 
-impl Readable for Witness {
-    fn read<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+impl Witness {
+    fn read_unlocated<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
         match buf.get_u8()? {
             WITNESS_TAG_OLDUTXO => {
-                let xpub = deserialize_public_key(buf)?;
-                let sig = deserialize_signature(buf)?;
+                let xpub = deserialize_public_key_with_label(buf, "old utxo witness public key")?;
+                let sig = deserialize_signature_with_label(buf, "old utxo witness signature")?;
                 Ok(Witness::OldUtxo(xpub, sig))
             }
-            WITNESS_TAG_UTXO => deserialize_signature(buf).map(Witness::Utxo),
-            WITNESS_TAG_ACCOUNT => deserialize_signature(buf).map(Witness::Account),
+            WITNESS_TAG_UTXO => {
+                deserialize_signature_with_label(buf, "utxo witness signature").map(Witness::Utxo)
+            }
+            WITNESS_TAG_ACCOUNT => {
+                deserialize_signature_with_label(buf, "account witness signature")
+                    .map(Witness::Account)
+            }
             i => Err(ReadError::UnknownTag(i as u32)),
         }
     }
 }
 
+/// Errors are located at the offset of the read that failed
+impl Readable for Witness {
+    fn read<'a>(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+        Self::read_unlocated(buf).map_err(|e| buf.locate_error(e))
+    }
+}
+
+impl property::Deserialize for Witness {
+    type Error = std::io::Error;
+
+    fn deserialize<R: std::io::BufRead>(mut reader: R) -> Result<Self, Self::Error> {
+        // the size of a witness depends on its tag, read exactly that
+        // many bytes and let the mempack reader decode them
+        let mut tag = [0u8; 1];
+        reader.read_exact(&mut tag)?;
+        let size = match tag[0] {
+            WITNESS_TAG_OLDUTXO => Ed25519Bip32::PUBLIC_KEY_SIZE + Ed25519Bip32::SIGNATURE_SIZE,
+            WITNESS_TAG_UTXO | WITNESS_TAG_ACCOUNT => Ed25519Extended::SIGNATURE_SIZE,
+            t => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("unknown witness tag {}", t),
+                ))
+            }
+        };
+        let mut bytes = vec![0u8; 1 + size];
+        bytes[0] = tag[0];
+        reader.read_exact(&mut bytes[1..])?;
+        read_from_raw(&bytes)
+    }
+}
+
+#[cfg(feature = "generic-serialization")]
+mod serde_impl {
+    use super::*;
+    use cardano::util::hex;
+    use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+    /// human readable form of a `Witness`, with keys and signatures
+    /// encoded in hexadecimal
+    #[derive(serde_derive::Serialize, serde_derive::Deserialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    enum WitnessRepr {
+        Utxo {
+            signature: String,
+        },
+        Account {
+            signature: String,
+        },
+        OldUtxo {
+            public_key: String,
+            signature: String,
+        },
+    }
+
+    impl Serialize for Witness {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match self {
+                Witness::Utxo(signature) => WitnessRepr::Utxo {
+                    signature: hex::encode(signature.as_ref()),
+                },
+                Witness::Account(signature) => WitnessRepr::Account {
+                    signature: hex::encode(signature.as_ref()),
+                },
+                Witness::OldUtxo(xpub, signature) => WitnessRepr::OldUtxo {
+                    public_key: hex::encode(xpub.as_ref()),
+                    signature: hex::encode(signature.as_ref()),
+                },
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Witness {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            fn signature<T, A, E>(s: &str) -> Result<Signature<T, A>, E>
+            where
+                A: chain_crypto::VerificationAlgorithm,
+                E: serde::de::Error,
+            {
+                let bytes = hex::decode(s).map_err(E::custom)?;
+                Signature::from_binary(&bytes).map_err(E::custom)
+            }
+
+            match WitnessRepr::deserialize(deserializer)? {
+                WitnessRepr::Utxo { signature: s } => signature(&s).map(Witness::Utxo),
+                WitnessRepr::Account { signature: s } => signature(&s).map(Witness::Account),
+                WitnessRepr::OldUtxo {
+                    public_key,
+                    signature: s,
+                } => {
+                    let bytes = hex::decode(&public_key).map_err(D::Error::custom)?;
+                    let xpub = PublicKey::from_binary(&bytes).map_err(D::Error::custom)?;
+                    Ok(Witness::OldUtxo(xpub, signature(&s)?))
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::super::test::TransactionSigningKey;
+        use super::*;
+        use chain_crypto::{Ed25519Bip32, KeyPair};
+        use quickcheck::{Arbitrary, StdThreadGen};
+
+        fn json_roundtrip(witness: Witness) {
+            let json = serde_json::to_string(&witness).unwrap();
+            let decoded: Witness = serde_json::from_str(&json).unwrap();
+            assert_eq!(decoded, witness, "json: {}", json);
+        }
+
+        #[test]
+        fn witness_json_roundtrip() {
+            let mut g = StdThreadGen::new(10);
+            let txid = TransactionId::arbitrary(&mut g);
+            let counter = account::SpendingCounter::from(u32::arbitrary(&mut g));
+            let sk = TransactionSigningKey::arbitrary(&mut g).0;
+            let old_key = KeyPair::<Ed25519Bip32>::arbitrary(&mut g);
+
+            json_roundtrip(Witness::new_utxo(&txid, &sk));
+            json_roundtrip(Witness::new_account(&txid, &counter, &sk));
+            json_roundtrip(Witness::new_old_utxo(&txid, old_key.private_key()));
+        }
+
+        #[test]
+        fn witness_json_format() {
+            let mut g = StdThreadGen::new(10);
+            let txid = TransactionId::arbitrary(&mut g);
+            let sk = TransactionSigningKey::arbitrary(&mut g).0;
+            let witness = Witness::new_utxo(&txid, &sk);
+            let signature = match &witness {
+                Witness::Utxo(signature) => hex::encode(signature.as_ref()),
+                _ => unreachable!(),
+            };
+            assert_eq!(
+                serde_json::to_value(&witness).unwrap(),
+                serde_json::json!({ "type": "utxo", "signature": signature })
+            );
+        }
+    }
+}
+
 #[cfg(test)]
 pub mod test {
     use super::*;
-    use quickcheck::{Arbitrary, Gen};
+    use quickcheck::{Arbitrary, Gen, TestResult};
 
     #[derive(Clone)]
     pub struct TransactionSigningKey(pub SpendingSecretKey);
@@ -162,15 +619,82 @@ pub mod test {
         }
     }
 
+    /// the key a legacy witness made with `keypair` is verified against
+    pub fn old_utxo_public_key(keypair: &chain_crypto::KeyPair<Ed25519Bip32>) -> SpendingPublicKey {
+        let key_size = <Ed25519Extended as AsymmetricKey>::PUBLIC_KEY_SIZE;
+        SpendingPublicKey::from_binary(&keypair.public_key().as_ref()[..key_size]).unwrap()
+    }
+
     impl Arbitrary for Witness {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
-            let sk = TransactionSigningKey::arbitrary(g);
             let txid = TransactionId::arbitrary(g);
-            Witness::Utxo(SpendingSignature::generate(&sk.0, &txid))
+            match g.next_u32() % 3 {
+                0 => {
+                    let sk = TransactionSigningKey::arbitrary(g);
+                    Witness::new_utxo(&txid, &sk.0)
+                }
+                1 => {
+                    let sk = TransactionSigningKey::arbitrary(g);
+                    let counter = account::SpendingCounter::from(u32::arbitrary(g));
+                    Witness::new_account(&txid, &counter, &sk.0)
+                }
+                _ => {
+                    let keypair = chain_crypto::KeyPair::<Ed25519Bip32>::arbitrary(g);
+                    Witness::new_old_utxo(&txid, keypair.private_key())
+                }
+            }
         }
     }
 
     quickcheck! {
+        fn witness_serialization_bijection(witness: Witness) -> TestResult {
+            property::testing::serialization_bijection(witness)
+        }
+
+        fn witness_serialization_bijection_r(witness: Witness) -> TestResult {
+            property::testing::serialization_bijection_r(witness)
+        }
+
+        fn witness_eq_matches_bytes_eq(witness1: Witness, witness2: Witness) -> bool {
+            use chain_core::property::Serialize;
+            let bytes1 = witness1.serialize_as_vec().unwrap();
+            let bytes2 = witness2.serialize_as_vec().unwrap();
+            witness1 == witness1.clone() && (witness1 == witness2) == (bytes1 == bytes2)
+        }
+
+        fn witness_serialized_size(
+            sk: TransactionSigningKey,
+            keypair: chain_crypto::KeyPair<Ed25519Bip32>,
+            tx: TransactionId,
+            counter: u32
+        ) -> bool {
+            use chain_core::property::Serialize;
+            let counter = account::SpendingCounter::from(counter);
+            [
+                Witness::new_utxo(&tx, &sk.0),
+                Witness::new_account(&tx, &counter, &sk.0),
+                Witness::new_old_utxo(&tx, keypair.private_key()),
+            ]
+            .iter()
+            .all(|witness| witness.serialized_size() == witness.serialize_as_vec().unwrap().len())
+        }
+
+        fn witness_old_utxo_serialization_bijection(
+            keypair: chain_crypto::KeyPair<Ed25519Bip32>,
+            tx: TransactionId
+        ) -> TestResult {
+            property::testing::serialization_bijection(Witness::new_old_utxo(&tx, keypair.private_key()))
+        }
+
+        fn transaction_id_spending_counter_layout(txid: TransactionId, counter: u32) -> bool {
+            let counter = account::SpendingCounter::from(counter);
+            let tidsc = TransactionIdSpendingCounter::new(&txid, &counter);
+
+            let mut expected = vec![WITNESS_TAG_ACCOUNT];
+            expected.extend_from_slice(txid.as_ref());
+            expected.extend_from_slice(&counter.to_bytes());
+            tidsc.as_bytes() == expected.as_slice() && tidsc.as_ref() == tidsc.as_bytes()
+        }
 
         /// ```
         /// \forall w=Witness(tx) => w.verifies(tx)
@@ -180,5 +704,290 @@ pub mod test {
             let witness = Witness::new_utxo(&tx, &sk.0);
             witness.verify_utxo(&pk, &tx) == Verification::Success
         }
+
+        fn prop_old_utxo_witness_verifies_own_tx(
+            keypair: chain_crypto::KeyPair<Ed25519Bip32>,
+            tx: TransactionId,
+            other_tx: TransactionId
+        ) -> bool {
+            let witness = Witness::new_old_utxo(&tx, keypair.private_key());
+            witness.verify_old_utxo(&tx) == Verification::Success
+                && (tx == other_tx || witness.verify_old_utxo(&other_tx) == Verification::Failed)
+        }
+
+        fn prop_verify_witnesses_mixed(
+            keys: Vec<TransactionSigningKey>,
+            old_key: chain_crypto::KeyPair<Ed25519Bip32>,
+            counter: u32,
+            tx: TransactionId,
+            other_tx: TransactionId
+        ) -> TestResult {
+            if keys.is_empty() || tx == other_tx {
+                return TestResult::discard();
+            }
+            let counter = account::SpendingCounter::from(counter);
+            let mut inputs: Vec<_> = keys
+                .iter()
+                .map(|sk| (sk.0.to_public(), None, Witness::new_utxo(&tx, &sk.0)))
+                .collect();
+            let public_key = keys[0].0.to_public();
+            let old_witness = Witness::new_old_utxo(&tx, old_key.private_key());
+            let account_witness = Witness::new_account(&tx, &counter, &keys[0].0);
+            inputs.push((old_utxo_public_key(&old_key), None, old_witness));
+            inputs.push((public_key.clone(), Some(counter), account_witness));
+            if verify_witnesses(&tx, &inputs) != Verification::Success {
+                return TestResult::failed();
+            }
+
+            // a witness signing another transaction invalidates the whole set
+            let mut invalid = inputs.clone();
+            invalid.insert(0, (public_key, None, Witness::new_utxo(&other_tx, &keys[0].0)));
+            TestResult::from_bool(verify_witnesses(&tx, &invalid) == Verification::Failed)
+        }
+    }
+
+    #[test]
+    fn verify_account_with_state() {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let tx = TransactionId::arbitrary(&mut g);
+        let sk = TransactionSigningKey::arbitrary(&mut g).0;
+        let pk = sk.to_public();
+        let counter = account::SpendingCounter::from(41);
+        let next_counter = counter.increment().unwrap();
+        let witness = Witness::new_account(&tx, &counter, &sk);
+
+        assert_eq!(
+            witness.verify_account_with_state(&pk, &tx, &counter),
+            Verification::Success
+        );
+        assert_eq!(
+            witness.verify_account_with_state(&pk, &tx, &account::SpendingCounter::from(7)),
+            Verification::Failed
+        );
+        // valid signature, but the account already moved past this counter
+        assert_eq!(
+            witness.verify_account_with_state(&pk, &tx, &next_counter),
+            Verification::Failed
+        );
+        assert_eq!(
+            Witness::new_utxo(&tx, &sk).verify_account_with_state(&pk, &tx, &counter),
+            Verification::Failed
+        );
+    }
+
+    #[test]
+    fn read_witness_error_position() {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let tx = TransactionId::arbitrary(&mut g);
+        let sk = TransactionSigningKey::arbitrary(&mut g).0;
+        let witness = Witness::new_utxo(&tx, &sk);
+        let mut bytes = property::Serialize::serialize_as_vec(&witness).unwrap();
+        let second_position = bytes.len();
+        bytes.extend(bytes.clone());
+        bytes[second_position] = 0xff;
+
+        let mut buf = ReadBuf::from(&bytes);
+        assert_eq!(Witness::read(&mut buf), Ok(witness));
+        assert_eq!(
+            Witness::read(&mut buf).unwrap_err(),
+            ReadError::AtPosition(second_position, Box::new(ReadError::UnknownTag(0xff)))
+        );
+    }
+
+    #[test]
+    fn read_truncated_witness_not_enough_bytes() {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let tx = TransactionId::arbitrary(&mut g);
+        let sk = TransactionSigningKey::arbitrary(&mut g).0;
+        let counter = account::SpendingCounter::from(0);
+        let witness = Witness::new_account(&tx, &counter, &sk);
+        let bytes = property::Serialize::serialize_as_vec(&witness).unwrap();
+
+        let mut buf = ReadBuf::from(&bytes[..bytes.len() - 1]);
+        let err = Witness::read(&mut buf).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("while reading account witness signature: NotEnoughBytes"),
+            "{}",
+            err
+        );
+        match err {
+            ReadError::AtPosition(_, e) => match *e {
+                ReadError::Context(_, e) => match *e {
+                    ReadError::NotEnoughBytes(_, _) => (),
+                    e => panic!("unexpected error {:?}", e),
+                },
+                e => panic!("unexpected error {:?}", e),
+            },
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn new_utxo_with_public_verifies() {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let tx = TransactionId::arbitrary(&mut g);
+        let sk = TransactionSigningKey::arbitrary(&mut g).0;
+
+        let (witness, public_key) = Witness::new_utxo_with_public(&tx, &sk);
+        assert_eq!(witness, Witness::new_utxo(&tx, &sk));
+        assert_eq!(public_key, sk.to_public());
+        assert_eq!(witness.verify_utxo(&public_key, &tx), Verification::Success);
+    }
+
+    #[test]
+    fn verify_witnesses_rejects_wrong_key_and_counter() {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let tx = TransactionId::arbitrary(&mut g);
+        let sk1 = TransactionSigningKey::arbitrary(&mut g).0;
+        let sk2 = TransactionSigningKey::arbitrary(&mut g).0;
+        let counter = account::SpendingCounter::from(0);
+        let next_counter = counter.increment().unwrap();
+
+        let valid = (sk1.to_public(), None, Witness::new_utxo(&tx, &sk1));
+        let wrong_key = (sk2.to_public(), None, Witness::new_utxo(&tx, &sk1));
+        let account_witness = Witness::new_account(&tx, &counter, &sk2);
+        let account = (sk2.to_public(), Some(counter), account_witness.clone());
+        let stale_counter = (sk2.to_public(), Some(next_counter), account_witness.clone());
+        let no_counter = (sk2.to_public(), None, account_witness);
+
+        assert_eq!(verify_witnesses(&tx, &[]), Verification::Success);
+        assert_eq!(
+            verify_witnesses(&tx, std::slice::from_ref(&valid)),
+            Verification::Success
+        );
+        assert_eq!(
+            verify_witnesses(&tx, &[valid.clone(), account.clone()]),
+            Verification::Success
+        );
+        assert_eq!(
+            verify_witnesses(&tx, &[valid.clone(), wrong_key]),
+            Verification::Failed
+        );
+        assert_eq!(
+            verify_witnesses(&tx, &[account.clone(), stale_counter]),
+            Verification::Failed
+        );
+        assert_eq!(
+            verify_witnesses(&tx, &[valid, account, no_counter]),
+            Verification::Failed
+        );
+    }
+
+    #[test]
+    fn verify_witnesses_rejects_old_utxo_with_wrong_key() {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let tx = TransactionId::arbitrary(&mut g);
+        let keypair = chain_crypto::KeyPair::<Ed25519Bip32>::arbitrary(&mut g);
+        let sk = TransactionSigningKey::arbitrary(&mut g).0;
+        let witness = Witness::new_old_utxo(&tx, keypair.private_key());
+
+        let valid = (old_utxo_public_key(&keypair), None, witness.clone());
+        let wrong_key = (sk.to_public(), None, witness);
+
+        assert_eq!(
+            verify_witnesses(&tx, std::slice::from_ref(&valid)),
+            Verification::Success
+        );
+        // the witness verifies against its own key, but not the expected one
+        assert_eq!(
+            verify_witnesses(&tx, std::slice::from_ref(&wrong_key)),
+            Verification::Failed
+        );
+        assert_eq!(
+            verify_witnesses(&tx, &[valid, wrong_key]),
+            Verification::Failed
+        );
+    }
+
+    #[test]
+    fn authenticated_input_verify() {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let tx = TransactionId::arbitrary(&mut g);
+        let other_tx = TransactionId::arbitrary(&mut g);
+        let input = Input::arbitrary(&mut g);
+        let sk1 = TransactionSigningKey::arbitrary(&mut g).0;
+        let sk2 = TransactionSigningKey::arbitrary(&mut g).0;
+        let witness = Witness::new_utxo(&tx, &sk1);
+
+        let authenticated =
+            AuthenticatedInput::verify(input.clone(), sk1.to_public(), None, witness.clone(), &tx)
+                .unwrap();
+        assert_eq!(authenticated.input(), &input);
+        assert_eq!(authenticated.public_key(), &sk1.to_public());
+        assert_eq!(authenticated.spending_counter(), None);
+        assert_eq!(authenticated.witness(), &witness);
+
+        assert_eq!(
+            AuthenticatedInput::verify(input.clone(), sk2.to_public(), None, witness.clone(), &tx),
+            Err(Verification::Failed)
+        );
+        assert_eq!(
+            AuthenticatedInput::verify(input, sk1.to_public(), None, witness, &other_tx),
+            Err(Verification::Failed)
+        );
+    }
+
+    #[test]
+    fn authenticated_input_verify_account() {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let tx = TransactionId::arbitrary(&mut g);
+        let input = Input::arbitrary(&mut g);
+        let sk = TransactionSigningKey::arbitrary(&mut g).0;
+        let counter = account::SpendingCounter::from(0);
+        let next_counter = counter.increment().unwrap();
+        let witness = Witness::new_account(&tx, &counter, &sk);
+
+        let authenticated = AuthenticatedInput::verify(
+            input.clone(),
+            sk.to_public(),
+            Some(counter),
+            witness.clone(),
+            &tx,
+        )
+        .unwrap();
+        assert_eq!(authenticated.spending_counter(), Some(&counter));
+        assert_eq!(authenticated.witness(), &witness);
+
+        assert_eq!(
+            AuthenticatedInput::verify(
+                input.clone(),
+                sk.to_public(),
+                Some(next_counter),
+                witness.clone(),
+                &tx
+            ),
+            Err(Verification::Failed)
+        );
+        assert_eq!(
+            AuthenticatedInput::verify(input, sk.to_public(), None, witness, &tx),
+            Err(Verification::Failed)
+        );
+    }
+
+    #[test]
+    fn authenticated_input_verify_old_utxo() {
+        let mut g = quickcheck::StdThreadGen::new(10);
+        let tx = TransactionId::arbitrary(&mut g);
+        let input = Input::arbitrary(&mut g);
+        let keypair = chain_crypto::KeyPair::<Ed25519Bip32>::arbitrary(&mut g);
+        let sk = TransactionSigningKey::arbitrary(&mut g).0;
+        let witness = Witness::new_old_utxo(&tx, keypair.private_key());
+
+        let authenticated = AuthenticatedInput::verify(
+            input.clone(),
+            old_utxo_public_key(&keypair),
+            None,
+            witness.clone(),
+            &tx,
+        )
+        .unwrap();
+        assert_eq!(authenticated.public_key(), &old_utxo_public_key(&keypair));
+
+        // the witness verifies against the key it carries, not the stored one
+        assert_eq!(
+            AuthenticatedInput::verify(input, sk.to_public(), None, witness, &tx),
+            Err(Verification::Failed)
+        );
     }
 }
@@ -1,5 +1,5 @@
 use crate::certificate as cert;
-use crate::fee::FeeAlgorithm;
+use crate::fee::{CertificateCount, FeeAlgorithm};
 use crate::transaction::{self as tx, Balance};
 use crate::value::{Value, ValueError};
 use chain_addr::Address;
@@ -77,7 +77,7 @@ impl<Address, Extra> From<tx::Transaction<Address, Extra>> for TransactionBuilde
     }
 }
 
-impl<Extra: Clone> TransactionBuilder<Address, Extra> {
+impl<Extra: Clone + CertificateCount> TransactionBuilder<Address, Extra> {
     /// Create new transaction builder.
 
     /// Add additional input.
@@ -12,6 +12,25 @@ impl Value {
         Value(0)
     }
 
+    /// `None` on overflow
+    pub fn checked_add(self, other: Value) -> Option<Value> {
+        self.0.checked_add(other.0).map(Value)
+    }
+
+    /// `None` if `other` is greater than `self`
+    pub fn checked_sub(self, other: Value) -> Option<Value> {
+        self.0.checked_sub(other.0).map(Value)
+    }
+
+    /// `None` on overflow
+    pub fn checked_mul(self, factor: u64) -> Option<Value> {
+        self.0.checked_mul(factor).map(Value)
+    }
+
+    pub fn saturating_add(self, other: Value) -> Value {
+        Value(self.0.saturating_add(other.0))
+    }
+
     pub fn sum<I>(values: I) -> Result<Self, ValueError>
     where
         I: Iterator<Item = Self>,
@@ -31,10 +50,7 @@ impl ops::Add for Value {
     type Output = Result<Value, ValueError>;
 
     fn add(self, other: Value) -> Self::Output {
-        self.0
-            .checked_add(other.0)
-            .map(Value)
-            .ok_or(ValueError::Overflow)
+        self.checked_add(other).ok_or(ValueError::Overflow)
     }
 }
 
@@ -42,10 +58,13 @@ impl ops::Sub for Value {
     type Output = Result<Value, ValueError>;
 
     fn sub(self, other: Value) -> Self::Output {
-        self.0
-            .checked_sub(other.0)
-            .map(Value)
-            .ok_or(ValueError::NegativeAmount)
+        self.checked_sub(other).ok_or(ValueError::NegativeAmount)
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
     }
 }
 
@@ -78,3 +97,37 @@ impl property::Serialize for Value {
         codec.put_u64(self.0)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use quickcheck::TestResult;
+
+    #[test]
+    fn checked_arithmetic_bounds() {
+        assert_eq!(Value(u64::MAX).checked_add(Value(1)), None);
+        assert_eq!(
+            Value(u64::MAX - 1).checked_add(Value(1)),
+            Some(Value(u64::MAX))
+        );
+        assert_eq!(Value(0).checked_sub(Value(1)), None);
+        assert_eq!(Value(3).checked_sub(Value(3)), Some(Value(0)));
+        assert_eq!(Value(u64::MAX).checked_mul(2), None);
+        assert_eq!(Value(21).checked_mul(2), Some(Value(42)));
+        assert_eq!(Value(u64::MAX).saturating_add(Value(1)), Value(u64::MAX));
+        assert_eq!(Value(u64::MAX) + Value(1), Err(ValueError::Overflow));
+        assert_eq!(Value(0) - Value(1), Err(ValueError::NegativeAmount));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Value(0).to_string(), "0");
+        assert_eq!(Value(u64::MAX).to_string(), u64::MAX.to_string());
+    }
+
+    quickcheck! {
+        fn value_serialization_bijection(value: u64) -> TestResult {
+            property::testing::serialization_bijection_r(Value(value))
+        }
+    }
+}
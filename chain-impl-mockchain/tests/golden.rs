@@ -0,0 +1,137 @@
+//! Golden vectors for the binary encoding of the mockchain types.
+//!
+//! These tests pin the wire format: if one of them fails after a
+//! refactoring, the encoding has changed. When the change is intended,
+//! the vector has to be updated deliberately.
+
+use chain_addr::Discrimination;
+use chain_core::mempack::{ReadBuf, Readable};
+use chain_core::property::Serialize;
+use chain_crypto::{Ed25519Extended, PublicKey, Signature};
+use chain_impl_mockchain::block::ConsensusVersion;
+use chain_impl_mockchain::config::{Block0Date, ConfigParam};
+use chain_impl_mockchain::fee::LinearFee;
+use chain_impl_mockchain::leadership::bft::LeaderId;
+use chain_impl_mockchain::message::InitialEnts;
+use chain_impl_mockchain::setting::{Percentage, TaxType, UpdateProposal};
+use chain_impl_mockchain::transaction::Witness;
+use chain_impl_mockchain::value::Value;
+use std::fmt::Debug;
+
+fn check_golden<T>(value: T, golden: &[u8])
+where
+    T: Serialize + Readable + PartialEq + Debug,
+    T::Error: Debug,
+{
+    assert_eq!(value.serialize_as_vec().unwrap(), golden, "serialization");
+
+    let mut buf = ReadBuf::from(golden);
+    let decoded = T::read(&mut buf).unwrap();
+    buf.expect_end().unwrap();
+    assert_eq!(decoded, value, "deserialization");
+}
+
+#[test]
+fn update_proposal_empty() {
+    check_golden(UpdateProposal::new(), &[0x00, 0x00]);
+}
+
+#[test]
+fn update_proposal_all_fields() {
+    let leader = PublicKey::<Ed25519Extended>::from_binary(&[0x11; 32]).unwrap();
+    let mut update = UpdateProposal::new();
+    update.max_number_of_transactions_per_block = Some(100);
+    update.bootstrap_key_slots_percentage = Some(Percentage::new(20).unwrap());
+    update.consensus_version = Some(ConsensusVersion::Bft);
+    update.bft_leaders = Some(vec![LeaderId::from(leader)]);
+    update.allow_account_creation = Some(true);
+    update.linear_fees = Some(LinearFee::new(1, 2, 3));
+    update.slot_duration = Some(10);
+    update.epoch_stability_depth = Some(2160);
+    update.treasury_tax = Some(TaxType::new(1, 10, Some(Value(1000))).unwrap());
+    update.max_block_content_size = Some(1024);
+    update.max_updates_per_block = Some(5);
+    update.max_certificates_per_block = Some(20);
+
+    let golden = [
+        // max number of transactions per block
+        &[0x00, 0x01, 0x00, 0x00, 0x00, 0x64][..],
+        // bootstrap key slots percentage
+        &[0x00, 0x02, 0x14],
+        // consensus version
+        &[0x00, 0x03, 0x00, 0x01],
+        // BFT leaders
+        &[0x00, 0x04, 0x01],
+        &[0x11; 32],
+        // allow account creation
+        &[0x00, 0x05, 0x01],
+        // linear fees: constant, coefficient, certificate
+        &[0x00, 0x06],
+        &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01],
+        &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02],
+        &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03],
+        // slot duration
+        &[0x00, 0x07, 0x0a],
+        // epoch stability depth
+        &[0x00, 0x08, 0x00, 0x00, 0x08, 0x70],
+        // treasury tax: numerator, denominator, cap
+        &[0x00, 0x09],
+        &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01],
+        &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0a],
+        &[0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xe8],
+        // max block content size
+        &[0x00, 0x0a, 0x00, 0x00, 0x04, 0x00],
+        // max updates per block
+        &[0x00, 0x0b, 0x00, 0x00, 0x00, 0x05],
+        // max certificates per block
+        &[0x00, 0x0c, 0x00, 0x00, 0x00, 0x14],
+        // end
+        &[0x00, 0x00],
+    ]
+    .concat();
+
+    check_golden(update, &golden);
+}
+
+#[test]
+fn witness_utxo() {
+    let signature = Signature::from_binary(&[0x22; 64]).unwrap();
+    let golden = [&[0x01][..], &[0x22; 64]].concat();
+    check_golden(Witness::Utxo(signature), &golden);
+}
+
+#[test]
+fn witness_account() {
+    let signature = Signature::from_binary(&[0x33; 64]).unwrap();
+    let golden = [&[0x02][..], &[0x33; 64]].concat();
+    check_golden(Witness::Account(signature), &golden);
+}
+
+#[test]
+fn witness_old_utxo() {
+    let public_key = PublicKey::from_binary(&[0x44; 64]).unwrap();
+    let signature = Signature::from_binary(&[0x55; 64]).unwrap();
+    let golden = [&[0x00][..], &[0x44; 64], &[0x55; 64]].concat();
+    check_golden(Witness::OldUtxo(public_key, signature), &golden);
+}
+
+#[test]
+fn initial_ents() {
+    let mut ents = InitialEnts::new();
+    ents.push(ConfigParam::Block0Date(Block0Date(1_000_000)));
+    ents.push(ConfigParam::Discrimination(Discrimination::Test));
+    ents.push(ConfigParam::ConsensusVersion(ConsensusVersion::Bft));
+
+    let golden = [
+        // block0 date: tag 1, 8 bytes
+        &[0x00, 0x48][..],
+        &[0x00, 0x00, 0x00, 0x00, 0x00, 0x0f, 0x42, 0x40],
+        // discrimination: tag 2, 1 byte
+        &[0x00, 0x81, 0x02],
+        // consensus version: tag 3, 2 bytes
+        &[0x00, 0xc2, 0x00, 0x01],
+    ]
+    .concat();
+
+    check_golden(ents, &golden);
+}